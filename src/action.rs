@@ -1,6 +1,7 @@
 use std::cmp;
 use std::fmt;
 
+use crate::board::Color;
 use crate::error::ParseError;
 use crate::parse;
 
@@ -128,7 +129,7 @@ pub enum ActionType {
 
 // source: 5, destination: 5, jump length: 5, jump directions: 8 * 2 bits (four directions), unused: 1
 /// Represents an action that can be made on a checkerboard
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Action(u32);
 
 impl Action {
@@ -276,6 +277,46 @@ impl Action {
         Direction::between(source, destination)
     }
 
+    /// Returns the action as seen from the other side of the board. The board is
+    /// turned 180 degrees so position `i` becomes `31 - i` and every direction
+    /// turns around.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Action;
+    ///
+    /// let action = Action::from_movetext("1-10-17").unwrap();
+    /// assert_eq!(action.rotated().movetext(), "32-23-16");
+    /// assert_eq!(action.rotated().rotated(), action);
+    /// ```
+    pub fn rotated(&self) -> Self {
+        let source = 31 - self.source() as u32;
+        let destination = 31 - self.destination() as u32;
+        let jump_len = self.jump_len() as u32;
+
+        // turning a direction around flips both of its bits
+        let flips = ((1_u32 << (2 * jump_len)) - 1) << 15;
+        let directions = (self.0 & (0xffff << 15)) ^ flips;
+
+        Action(source | destination << 5 | jump_len << 10 | directions)
+    }
+
+    /// Returns the action relative to the side making it, so that the mover is
+    /// always moving up the board like white does. Actions by white are left as
+    /// they are and actions by black are [rotated](#method.rotated).
+    ///
+    /// Applying the same conversion to a relative action gives back the absolute one.
+    /// Relative actions are valid on the matching
+    /// [relative board](struct.Bitboard.html#method.relative).
+    #[inline]
+    pub fn relative(&self, mover: Color) -> Self {
+        match mover {
+            Color::White => *self,
+            Color::Black => self.rotated(),
+        }
+    }
+
     /// Generate movetext for a particular action
     pub fn movetext(&self) -> String {
         let source = self.source();
@@ -396,4 +437,24 @@ mod tests {
         let action = Action::from_movetext(TEST_MOVE_4).unwrap();
         assert_eq!(action.move_direction(), Some(Direction::UpRight));
     }
+
+    #[test]
+    fn relative_action_test() {
+        let action = Action::from_movetext(TEST_MOVE_2).unwrap();
+        assert_eq!(action.relative(Color::Black).movetext(), "32-27");
+        assert_eq!(
+            action.relative(Color::Black).move_direction(),
+            Some(Direction::UpLeft)
+        );
+        assert_eq!(action.relative(Color::White), action);
+
+        let action = Action::from_movetext(TEST_MOVE_3).unwrap();
+        let rotated = action.relative(Color::Black);
+        assert_eq!(rotated.movetext(), "23-14-21-30");
+        assert_eq!(rotated.jump_direction(0), Some(Direction::UpLeft));
+        assert_eq!(rotated.jump_direction(1), Some(Direction::DownLeft));
+        assert_eq!(rotated.jump_direction(2), Some(Direction::DownRight));
+        assert_eq!(rotated.jump_direction(3), None);
+        assert_eq!(rotated.relative(Color::Black), action);
+    }
 }
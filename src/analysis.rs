@@ -0,0 +1,755 @@
+use std::fmt;
+
+use crate::board::{Action, Bitboard, Color};
+use crate::opening;
+use crate::pdn::PdnGame;
+use crate::search::{Engine, Optim, Score, SearchConstraint, Searchable, Side};
+
+// the first ten moves of each side count as the opening
+const OPENING_PLIES: usize = 20;
+const ENDGAME_PIECES: u32 = 10;
+
+// losses are capped so a single missed win does not swamp a player's average
+const MAX_CENTI_MAN_LOSS: f32 = 1000.;
+
+// a one man lead is worth roughly a 70% chance of winning
+const WIN_PROBABILITY_SCALE: f32 = 0.85;
+
+// fitted curve from the drop in winning chances to the accuracy of a move,
+// the same shape lichess uses for chess
+const ACCURACY_SCALE: f32 = 103.1668;
+const ACCURACY_DECAY: f32 = 0.04354;
+const ACCURACY_OFFSET: f32 = 3.1669;
+
+// drops in winning chances (in percent) for a move to be judged
+const INACCURACY_DROP: f32 = 10.;
+const MISTAKE_DROP: f32 = 20.;
+const BLUNDER_DROP: f32 = 30.;
+
+// how many plies of the opponent's punishing line are kept for judged moves
+const REFUTATION_PLIES: usize = 5;
+
+/// Returns the chance in percent that black wins from a position with the given
+/// score. Decided positions with infinite scores map to 0 or 100.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::analysis::win_probability;
+/// use muskox::search::Score;
+///
+/// assert_eq!(win_probability(Score::from(0.)), 50.);
+/// assert_eq!(win_probability(Score::from(f32::INFINITY)), 100.);
+/// assert!(win_probability(Score::from(-1.)) < 50.);
+/// ```
+pub fn win_probability(score: Score) -> f32 {
+    let score = f32::from(score);
+    50. + 50. * (2. / (1. + (-WIN_PROBABILITY_SCALE * score).exp()) - 1.)
+}
+
+/// Represents how bad a move was judged to be by its drop in winning chances
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Judgement::Inaccuracy => write!(f, "inaccuracy"),
+            Judgement::Mistake => write!(f, "mistake"),
+            Judgement::Blunder => write!(f, "blunder"),
+        }
+    }
+}
+
+/// Represents the phase of the game a move was made in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl Phase {
+    /// Returns the phase of a position `ply` half moves into a game. Positions with
+    /// few pieces left are endgames no matter how early they come up.
+    pub fn of(board: &Bitboard, ply: usize) -> Self {
+        let pieces = (board.blacks() | board.whites()).count_ones();
+
+        if pieces <= ENDGAME_PIECES {
+            Phase::Endgame
+        } else if ply < OPENING_PLIES {
+            Phase::Opening
+        } else {
+            Phase::Middlegame
+        }
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        match self {
+            Phase::Opening => 0,
+            Phase::Middlegame => 1,
+            Phase::Endgame => 2,
+        }
+    }
+}
+
+/// Represents how a single move compares to the reference engine's choice
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveAnalysis {
+    board: Bitboard,
+    played: Action,
+    best: Action,
+    played_score: Score,
+    best_score: Score,
+    forced: bool,
+    refutation: Vec<Action>,
+}
+
+impl MoveAnalysis {
+    #[inline]
+    pub fn board(&self) -> Bitboard {
+        self.board
+    }
+
+    #[inline]
+    pub fn played(&self) -> Action {
+        self.played
+    }
+
+    #[inline]
+    pub fn best(&self) -> Action {
+        self.best
+    }
+
+    #[inline]
+    pub fn played_score(&self) -> Score {
+        self.played_score
+    }
+
+    #[inline]
+    pub fn best_score(&self) -> Score {
+        self.best_score
+    }
+
+    /// Returns whether the move was the only legal one
+    #[inline]
+    pub fn forced(&self) -> bool {
+        self.forced
+    }
+
+    /// Returns the line the engine expects after the played move, starting with the
+    /// opponent's reply. It is only searched for judged moves in games analyzed by
+    /// an [Analyzer](struct.Analyzer.html) and is empty otherwise.
+    #[inline]
+    pub fn refutation(&self) -> &[Action] {
+        &self.refutation
+    }
+
+    /// Returns how much worse the played move is than the best move for the mover
+    /// in men. Moves that score as well as the best move lose nothing.
+    pub fn loss(&self) -> Score {
+        if self.played_score == self.best_score {
+            return Score::from(0.);
+        }
+
+        match self.board.turn().optim() {
+            Optim::Max => self.best_score - self.played_score,
+            Optim::Min => self.played_score - self.best_score,
+        }
+    }
+
+    /// Returns the loss in hundredths of a man, capped for moves that throw away
+    /// a won game
+    pub fn centi_man_loss(&self) -> f32 {
+        (f32::from(self.loss()) * 100.).min(MAX_CENTI_MAN_LOSS)
+    }
+
+    /// Returns whether the move was as good as the engine's choice. Moves that
+    /// tie with the engine's top move count as a match.
+    #[inline]
+    pub fn is_engine_match(&self) -> bool {
+        self.played_score == self.best_score
+    }
+
+    /// Returns how many percent the mover's winning chances dropped by playing
+    /// this move instead of the best one
+    pub fn win_probability_drop(&self) -> f32 {
+        if self.is_engine_match() {
+            return 0.;
+        }
+
+        let drop = win_probability(self.best_score) - win_probability(self.played_score);
+
+        match self.board.turn().optim() {
+            Optim::Max => drop.max(0.),
+            Optim::Min => (-drop).max(0.),
+        }
+    }
+
+    /// Returns the accuracy of the move from 0 to 100, where 100 means the move
+    /// kept all of the mover's winning chances
+    pub fn accuracy(&self) -> f32 {
+        let drop = self.win_probability_drop();
+        (ACCURACY_SCALE * (-ACCURACY_DECAY * drop).exp() - ACCURACY_OFFSET).clamp(0., 100.)
+    }
+
+    /// Returns a judgement if the move dropped enough winning chances. Forced
+    /// moves are never judged.
+    pub fn judgement(&self) -> Option<Judgement> {
+        if self.forced {
+            return None;
+        }
+
+        let drop = self.win_probability_drop();
+
+        if drop >= BLUNDER_DROP {
+            Some(Judgement::Blunder)
+        } else if drop >= MISTAKE_DROP {
+            Some(Judgement::Mistake)
+        } else if drop >= INACCURACY_DROP {
+            Some(Judgement::Inaccuracy)
+        } else {
+            None
+        }
+    }
+}
+
+/// Searches a position and compares the move played in it to the engine's best
+/// move. Returns `None` if the position has no moves or the played move is invalid.
+pub fn analyze_move(
+    engine: &mut Engine<Bitboard>,
+    constraint: &SearchConstraint,
+    board: &Bitboard,
+    played: Action,
+) -> Option<MoveAnalysis> {
+    let results = engine.search(board, constraint);
+
+    let best = results.first()?;
+    let played_score = results.iter().find(|p| p.action() == played)?.score();
+
+    Some(MoveAnalysis {
+        board: *board,
+        played,
+        best: best.action(),
+        played_score,
+        best_score: best.score(),
+        forced: results.len() == 1,
+        refutation: Vec::new(),
+    })
+}
+
+/// Follows the engine's best move from a position for up to `plies` half moves.
+/// The line is shorter if the game ends first.
+pub fn best_line(
+    engine: &mut Engine<Bitboard>,
+    constraint: &SearchConstraint,
+    board: &Bitboard,
+    plies: usize,
+) -> Vec<Action> {
+    let mut board = *board;
+    let mut line = Vec::with_capacity(plies);
+
+    while line.len() < plies {
+        let action = match engine.search(&board, constraint).first() {
+            Some(p) => p.action(),
+            None => break,
+        };
+
+        // the engine only ever suggests valid actions
+        board = board.take_action(action).unwrap();
+        line.push(action);
+    }
+
+    line
+}
+
+/// Represents every analyzed move of a single game
+pub struct GameAnalysis {
+    black: String,
+    white: String,
+    moves: Vec<(Phase, MoveAnalysis)>,
+}
+
+impl GameAnalysis {
+    #[inline]
+    pub fn player(&self, color: Color) -> &str {
+        match color {
+            Color::Black => &self.black,
+            Color::White => &self.white,
+        }
+    }
+
+    /// Returns the analysis of each move in order along with its phase
+    #[inline]
+    pub fn moves(&self) -> &[(Phase, MoveAnalysis)] {
+        &self.moves
+    }
+
+    /// Returns the totals over the unforced moves of one side
+    pub fn stats(&self, color: Color) -> Stats {
+        let mut stats = Stats::default();
+        for (_, analysis) in self.moves.iter() {
+            if analysis.board.turn() == color && !analysis.forced {
+                stats.add(analysis);
+            }
+        }
+        stats
+    }
+
+    /// Returns a copy of the game with the accuracy of both sides and the name of
+    /// the opening in its tags and a comment on every move that was judged. A side
+    /// without any unforced moves gets no accuracy tag. The
+    /// refutation of a judged move is added as a variation where it first differs
+    /// from the moves actually played, or to the comment if the game followed it
+    /// to the end.
+    pub fn annotate(&self, game: &PdnGame) -> PdnGame {
+        let mut annotated = game.clone();
+
+        for &(color, tag) in &[
+            (Color::Black, "BlackAccuracy"),
+            (Color::White, "WhiteAccuracy"),
+        ] {
+            if let Some(accuracy) = self.stats(color).accuracy() {
+                annotated.set_tag(tag, &format!("{:.1}", accuracy));
+            }
+        }
+
+        // opening names only apply to games from the starting position
+        if game.tag("Opening").is_none() && game.start() == Bitboard::default() {
+            if let Some(opening) = opening::classify(game.actions()) {
+                annotated.set_tag("Opening", opening.name());
+            }
+        }
+
+        for (ply, (_, analysis)) in self.moves.iter().enumerate() {
+            let judgement = match analysis.judgement() {
+                Some(judgement) => judgement,
+                None => continue,
+            };
+
+            let mut comment = format!("{}, {} was best", judgement, analysis.best());
+
+            if !analysis.refutation.is_empty() {
+                // the moves the game shares with the refutation need no variation
+                let played = &game.actions()[ply + 1..];
+                let shared = analysis
+                    .refutation
+                    .iter()
+                    .zip(played)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+
+                let at = ply + 1 + shared;
+                if shared < analysis.refutation.len()
+                    && at < game.actions().len()
+                    && annotated.variation(at).is_none()
+                {
+                    annotated.set_variation(at, &analysis.refutation[shared..]);
+                } else {
+                    let movetexts: Vec<_> =
+                        analysis.refutation.iter().map(|a| a.movetext()).collect();
+                    comment.push_str(&format!(", refuted by {}", movetexts.join(" ")));
+                }
+            }
+
+            annotated.set_comment(ply, &comment);
+        }
+
+        annotated
+    }
+}
+
+/// Running totals of the moves of one player
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Stats {
+    moves: usize,
+    matches: usize,
+    total_loss: f32,
+    total_accuracy: f32,
+}
+
+impl Stats {
+    fn add(&mut self, analysis: &MoveAnalysis) {
+        self.moves += 1;
+        if analysis.is_engine_match() {
+            self.matches += 1;
+        }
+        self.total_loss += analysis.centi_man_loss();
+        self.total_accuracy += analysis.accuracy();
+    }
+
+    /// Returns the number of moves counted, which excludes forced moves
+    #[inline]
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Returns the percentage of moves that matched the engine
+    pub fn match_rate(&self) -> f32 {
+        match self.moves {
+            0 => 0.,
+            n => self.matches as f32 * 100. / n as f32,
+        }
+    }
+
+    /// Returns the average centi-man loss per move
+    pub fn average_loss(&self) -> f32 {
+        match self.moves {
+            0 => 0.,
+            n => self.total_loss / n as f32,
+        }
+    }
+
+    /// Returns the average accuracy per move from 0 to 100, or `None` if no
+    /// moves were counted
+    pub fn accuracy(&self) -> Option<f32> {
+        match self.moves {
+            0 => None,
+            n => Some(self.total_accuracy / n as f32),
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let accuracy = match self.accuracy() {
+            Some(accuracy) => format!("{:.1}", accuracy),
+            None => "-".to_string(),
+        };
+
+        write!(
+            f,
+            "{} moves, {} accuracy, {:.1}% engine match, {:.1} average centi-man loss",
+            self.moves,
+            accuracy,
+            self.match_rate(),
+            self.average_loss()
+        )
+    }
+}
+
+/// Represents the statistics of a single player over one or more games
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerReport {
+    name: String,
+    overall: Stats,
+    phases: [Stats; 3],
+}
+
+impl PlayerReport {
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn overall(&self) -> Stats {
+        self.overall
+    }
+
+    #[inline]
+    pub fn phase(&self, phase: Phase) -> Stats {
+        self.phases[phase.index()]
+    }
+}
+
+impl fmt::Display for PlayerReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: {}", self.name, self.overall)?;
+        writeln!(f, "  opening: {}", self.phase(Phase::Opening))?;
+        writeln!(f, "  middlegame: {}", self.phase(Phase::Middlegame))?;
+        write!(f, "  endgame: {}", self.phase(Phase::Endgame))
+    }
+}
+
+/// Aggregates move analyses per player across a set of games. Players are told
+/// apart by the names in the `Black` and `White` tags.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Report {
+    players: Vec<PlayerReport>,
+}
+
+impl Report {
+    pub fn new() -> Self {
+        Report {
+            players: Vec::new(),
+        }
+    }
+
+    /// Adds all of the unforced moves in a game to the players' totals
+    pub fn add(&mut self, game: &GameAnalysis) {
+        for (phase, analysis) in game.moves.iter() {
+            if analysis.forced {
+                continue;
+            }
+
+            let name = game.player(analysis.board.turn());
+
+            let index = match self.players.iter().position(|p| p.name == name) {
+                Some(index) => index,
+                None => {
+                    self.players.push(PlayerReport {
+                        name: name.to_string(),
+                        overall: Stats::default(),
+                        phases: [Stats::default(); 3],
+                    });
+                    self.players.len() - 1
+                }
+            };
+
+            let player = &mut self.players[index];
+            player.overall.add(analysis);
+            player.phases[phase.index()].add(analysis);
+        }
+    }
+
+    #[inline]
+    pub fn players(&self) -> &[PlayerReport] {
+        &self.players
+    }
+
+    pub fn player(&self, name: &str) -> Option<&PlayerReport> {
+        self.players.iter().find(|p| p.name == name)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let players: Vec<_> = self.players.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}", players.join("\n"))
+    }
+}
+
+/// Compares the moves of finished games against a reference engine. The search
+/// constraint sets how strong the reference is.
+///
+/// # Examples
+///
+/// ```no_run
+/// use muskox::analysis::Analyzer;
+/// use muskox::pdn::PdnGame;
+/// use muskox::search::SearchConstraint;
+///
+/// let games = PdnGame::parse_all("[Black \"a\"]\n[White \"b\"]\n1. 11-15 23-19 2. 8-11 22-17 *").unwrap();
+///
+/// let mut analyzer = Analyzer::new(SearchConstraint::depth(10).unwrap());
+/// println!("{}", analyzer.report(&games));
+/// ```
+pub struct Analyzer {
+    engine: Engine<Bitboard>,
+    constraint: SearchConstraint,
+}
+
+impl Analyzer {
+    pub fn new(constraint: SearchConstraint) -> Self {
+        Analyzer::with_engine(Engine::new(), constraint)
+    }
+
+    pub fn with_engine(engine: Engine<Bitboard>, constraint: SearchConstraint) -> Self {
+        Analyzer { engine, constraint }
+    }
+
+    #[inline]
+    pub fn analyze_move(&mut self, board: &Bitboard, played: Action) -> Option<MoveAnalysis> {
+        analyze_move(&mut self.engine, &self.constraint, board, played)
+    }
+
+    /// Analyzes every move of a game. Analysis stops at the first invalid move.
+    pub fn analyze_game(&mut self, game: &PdnGame) -> GameAnalysis {
+        let mut board = game.start();
+        let mut moves = Vec::with_capacity(game.actions().len());
+
+        for (ply, &action) in game.actions().iter().enumerate() {
+            let mut analysis = match self.analyze_move(&board, action) {
+                Some(analysis) => analysis,
+                None => break,
+            };
+
+            let phase = Phase::of(&board, ply);
+
+            board = match board.take_action(action) {
+                Ok(board_p) => board_p,
+                Err(_) => break,
+            };
+
+            // show how the opponent punishes the move
+            if analysis.judgement().is_some() {
+                analysis.refutation =
+                    best_line(&mut self.engine, &self.constraint, &board, REFUTATION_PLIES);
+            }
+
+            moves.push((phase, analysis));
+        }
+
+        GameAnalysis {
+            black: game.tag("Black").unwrap_or("Black").to_string(),
+            white: game.tag("White").unwrap_or("White").to_string(),
+            moves,
+        }
+    }
+
+    /// Analyzes every game and aggregates the results per player
+    pub fn report(&mut self, games: &[PdnGame]) -> Report {
+        let mut report = Report::new();
+        for game in games {
+            report.add(&self.analyze_game(game));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_BOARD_1: &'static str = "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29";
+    const TEST_BOARD_2: &'static str = "W:W9,K11,19,K26,27,30:B15,22,25,K32";
+
+    fn analysis(fen: &str, played: f32, best: f32, forced: bool) -> MoveAnalysis {
+        MoveAnalysis {
+            board: Bitboard::from_fen(fen).unwrap(),
+            played: Action::from_movetext("1-6").unwrap(),
+            best: Action::from_movetext("1-5").unwrap(),
+            played_score: Score::from(played),
+            best_score: Score::from(best),
+            forced,
+            refutation: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn phase_test() {
+        let board = Bitboard::default();
+        assert_eq!(Phase::of(&board, 0), Phase::Opening);
+        assert_eq!(Phase::of(&board, 20), Phase::Middlegame);
+
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+        assert_eq!(Phase::of(&board, 30), Phase::Middlegame);
+
+        let board = Bitboard::from_fen(TEST_BOARD_2).unwrap();
+        assert_eq!(Phase::of(&board, 0), Phase::Endgame);
+    }
+
+    #[test]
+    fn move_loss_test() {
+        // black maximizes and white minimizes
+        let a = analysis(TEST_BOARD_1, 1., 2.5, false);
+        assert_eq!(a.loss(), Score::from(1.5));
+        assert_eq!(a.centi_man_loss(), 150.);
+        assert!(!a.is_engine_match());
+
+        let a = analysis(TEST_BOARD_2, -1., -3., false);
+        assert_eq!(a.loss(), Score::from(2.));
+
+        let a = analysis(TEST_BOARD_1, 0., f32::INFINITY, false);
+        assert_eq!(a.centi_man_loss(), 1000.);
+
+        let a = analysis(TEST_BOARD_1, f32::INFINITY, f32::INFINITY, false);
+        assert_eq!(a.loss(), Score::from(0.));
+        assert!(a.is_engine_match());
+    }
+
+    #[test]
+    fn accuracy_test() {
+        let a = analysis(TEST_BOARD_1, 1., 1., false);
+        assert_eq!(a.win_probability_drop(), 0.);
+        assert!(a.accuracy() > 99.9);
+        assert_eq!(a.judgement(), None);
+
+        let a = analysis(TEST_BOARD_1, 0., f32::INFINITY, false);
+        assert_eq!(a.win_probability_drop(), 50.);
+        assert_eq!(a.judgement(), Some(Judgement::Blunder));
+        assert!(a.accuracy() < 12.);
+
+        let a = analysis(TEST_BOARD_1, 0., f32::INFINITY, true);
+        assert_eq!(a.judgement(), None);
+
+        // white is better off with lower scores
+        let a = analysis(TEST_BOARD_2, -0.5, -1.5, false);
+        assert_eq!(a.judgement(), Some(Judgement::Inaccuracy));
+        assert!(a.accuracy() > 40. && a.accuracy() < 70.);
+    }
+
+    #[test]
+    fn report_test() {
+        let game = GameAnalysis {
+            black: "a".to_string(),
+            white: "b".to_string(),
+            moves: vec![
+                (Phase::Endgame, analysis(TEST_BOARD_1, 1., 1., false)),
+                (Phase::Endgame, analysis(TEST_BOARD_2, -1., -3., false)),
+                (Phase::Endgame, analysis(TEST_BOARD_1, 0., 1., false)),
+                (Phase::Endgame, analysis(TEST_BOARD_2, -1., -3., true)),
+            ],
+        };
+
+        let mut report = Report::new();
+        report.add(&game);
+        report.add(&game);
+
+        let a = report.player("a").unwrap();
+        assert_eq!(a.overall().moves(), 4);
+        assert_eq!(a.overall().match_rate(), 50.);
+        assert_eq!(a.overall().average_loss(), 50.);
+        assert_eq!(a.phase(Phase::Opening).moves(), 0);
+        assert_eq!(a.phase(Phase::Opening).accuracy(), None);
+
+        let b = report.player("b").unwrap();
+        assert_eq!(b.phase(Phase::Endgame).moves(), 2);
+        assert_eq!(b.phase(Phase::Endgame).average_loss(), 200.);
+    }
+
+    #[test]
+    fn annotate_test() {
+        let moves = |m: &[&str]| -> Vec<Action> {
+            m.iter()
+                .map(|m| Action::from_movetext(m).unwrap())
+                .collect()
+        };
+
+        let game = PdnGame::new(
+            Vec::new(),
+            Bitboard::default(),
+            moves(&["11-15", "22-18", "15-22"]),
+        );
+
+        // the game follows the refutation for one move before it differs
+        let mut blunder = analysis(TEST_BOARD_1, -3., 1., false);
+        blunder.refutation = moves(&["22-18", "12-16"]);
+
+        // the game follows the refutation to the end
+        let mut last = analysis(TEST_BOARD_2, 1., -1., false);
+        last.refutation = moves(&["15-22"]);
+
+        let analysis = GameAnalysis {
+            black: "a".to_string(),
+            white: "b".to_string(),
+            moves: vec![(Phase::Opening, blunder), (Phase::Opening, last)],
+        };
+
+        let annotated = analysis.annotate(&game);
+        assert_eq!(annotated.tag("Opening"), Some("Single Corner"));
+        assert_eq!(annotated.comment(0), Some("blunder, 1-5 was best"));
+        assert_eq!(annotated.variation(1), None);
+        assert_eq!(annotated.variation(2), Some(&moves(&["12-16"])[..]));
+        assert_eq!(
+            annotated.comment(1),
+            Some("blunder, 1-5 was best, refuted by 15-22")
+        );
+        assert!(annotated.tag("BlackAccuracy").is_some());
+
+        // no moves to judge means no accuracy either
+        let empty = GameAnalysis {
+            moves: Vec::new(),
+            ..analysis
+        };
+        let annotated = empty.annotate(&game);
+        assert_eq!(annotated.tag("BlackAccuracy"), None);
+        assert_eq!(annotated.tag("WhiteAccuracy"), None);
+    }
+}
@@ -0,0 +1,546 @@
+use std::time::{Duration, Instant};
+
+use crate::board::{Action, Bitboard, Color};
+use crate::error::OddsError;
+use crate::pdn::{self, PdnGame};
+use crate::search::{Engine, GameState, Score, SearchConstraint, Searchable, Winner};
+
+// games that run this long without a winner are adjudicated as draws since the
+// board cannot detect draws on its own yet
+const DEFAULT_MAX_PLIES: usize = 200;
+
+/// Represents one side of a match: the name written into the PDN tags and the
+/// search constraint its engine plays with
+#[derive(Debug, Clone)]
+pub struct Contestant {
+    name: String,
+    constraint: SearchConstraint,
+    finishing: Option<Score>,
+}
+
+impl Contestant {
+    pub fn new(name: &str, constraint: SearchConstraint) -> Self {
+        Contestant {
+            name: name.to_string(),
+            constraint,
+            finishing: None,
+        }
+    }
+
+    /// Plays with the engine's [finishing mode](../search/struct.Engine.html#method.finishing)
+    /// so won games are converted quickly
+    pub fn finishing(mut self, threshold: Score) -> Self {
+        self.finishing = Some(threshold);
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn constraint(&self) -> SearchConstraint {
+        self.constraint
+    }
+
+    fn engine(&self) -> Engine<Bitboard> {
+        match self.finishing {
+            Some(threshold) => Engine::new().finishing(threshold),
+            None => Engine::new(),
+        }
+    }
+}
+
+/// Runs a single engine versus engine game with optional handicaps.
+///
+/// Two kinds of odds are supported. Time odds multiply the thinking time of one
+/// side, and material odds remove men from the starting position. Both are
+/// recorded in the tags of the resulting PDN so that games played at odds can be
+/// told apart later.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::arena::{Contestant, Match};
+/// use muskox::board::Color;
+/// use muskox::search::SearchConstraint;
+///
+/// let strong = Contestant::new("strong", SearchConstraint::time(100).unwrap());
+/// let weak = Contestant::new("weak", SearchConstraint::time(100).unwrap());
+///
+/// let game = Match::new(strong, weak)
+///     .time_odds(Color::White, 2).unwrap()
+///     .material_odds(&[9]).unwrap();
+/// assert_eq!(game.start_position().fen(), "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,10,11,12");
+/// ```
+pub struct Match {
+    black: Contestant,
+    white: Contestant,
+    time_odds: Option<(Color, u32)>,
+    material_odds: Vec<u8>,
+    max_plies: usize,
+}
+
+impl Match {
+    pub fn new(black: Contestant, white: Contestant) -> Self {
+        Match {
+            black,
+            white,
+            time_odds: None,
+            material_odds: Vec::new(),
+            max_plies: DEFAULT_MAX_PLIES,
+        }
+    }
+
+    /// Gives `color` `factor` times the thinking time of its opponent. The
+    /// contestant receiving the odds must be playing with a timed constraint.
+    pub fn time_odds(mut self, color: Color, factor: u32) -> Result<Self, OddsError> {
+        if factor == 0 {
+            return Err(OddsError::TimeOddsFactorError);
+        }
+
+        match self.contestant(color).constraint {
+            SearchConstraint::Time(_) => (),
+            _ => return Err(OddsError::TimeOddsConstraintError { color }),
+        }
+
+        self.time_odds = Some((color, factor));
+        Ok(self)
+    }
+
+    /// Removes the men on the given standard positions (1 - 32) from the starting
+    /// position. Every position must hold a man at the start of a game.
+    pub fn material_odds(mut self, positions: &[u8]) -> Result<Self, OddsError> {
+        let start = Bitboard::default();
+        let occupied = start.blacks() | start.whites();
+
+        for &position in positions {
+            if !(1..=32).contains(&position) || (occupied >> (position - 1)) % 2 == 0 {
+                return Err(OddsError::MaterialOddsError { position });
+            }
+        }
+
+        let mut material_odds = positions.to_vec();
+        material_odds.sort_unstable();
+        material_odds.dedup();
+
+        self.material_odds = material_odds;
+        Ok(self)
+    }
+
+    /// Sets the number of plies after which the game is adjudicated as a draw
+    pub fn max_plies(mut self, max_plies: usize) -> Self {
+        self.max_plies = max_plies;
+        self
+    }
+
+    /// Returns the position the game starts from after the material odds are applied
+    pub fn start_position(&self) -> Bitboard {
+        let start = Bitboard::default();
+
+        let removed = self
+            .material_odds
+            .iter()
+            .fold(0, |mask, position| mask | 1 << (position - 1));
+
+        Bitboard::new(
+            start.blacks() & !removed,
+            start.whites() & !removed,
+            start.kings() & !removed,
+            start.turn(),
+        )
+    }
+
+    /// Plays the game out with a fresh engine for each side
+    pub fn play(&self) -> MatchGame {
+        let start = self.start_position();
+
+        let mut black_engine = self.black.engine();
+        let mut white_engine = self.white.engine();
+
+        let mut board = start;
+        let mut actions = Vec::new();
+        let mut telemetry = Vec::new();
+        let mut stuck = false;
+
+        while board.get_game_state() == GameState::InProgress && actions.len() < self.max_plies {
+            let constraint = self.constraint(board.turn());
+
+            let engine = match board.turn() {
+                Color::Black => &mut black_engine,
+                Color::White => &mut white_engine,
+            };
+
+            let started = Instant::now();
+            let (results, info) = engine.search_info(&board, &constraint);
+            let time = started.elapsed();
+
+            let (action, score) = match results.first() {
+                Some(p) => (p.action(), p.score()),
+                None => {
+                    stuck = true;
+                    break;
+                }
+            };
+
+            // the engine only ever suggests valid actions
+            board = board.take_action(action).unwrap();
+            actions.push(action);
+
+            telemetry.push(MoveTelemetry {
+                score,
+                depth: info.depth(),
+                nodes: info.nodes(),
+                time,
+            });
+        }
+
+        // a side left without moves loses even if the board does not report it
+        let result = match board.get_game_state() {
+            GameState::Completed(winner) => winner,
+            GameState::InProgress if stuck => Winner::Player(board.turn().opponent()),
+            GameState::InProgress => Winner::Draw,
+        };
+
+        MatchGame {
+            tags: self.tags(&start),
+            start,
+            actions,
+            telemetry,
+            result,
+        }
+    }
+
+    #[inline]
+    fn contestant(&self, color: Color) -> &Contestant {
+        match color {
+            Color::Black => &self.black,
+            Color::White => &self.white,
+        }
+    }
+
+    /// Returns the search constraint for a side with the time odds applied
+    fn constraint(&self, color: Color) -> SearchConstraint {
+        let constraint = self.contestant(color).constraint;
+
+        match (self.time_odds, constraint) {
+            (Some((odds_color, factor)), SearchConstraint::Time(duration))
+                if odds_color == color =>
+            {
+                SearchConstraint::Time(duration * factor)
+            }
+            _ => constraint,
+        }
+    }
+
+    fn tags(&self, start: &Bitboard) -> Vec<(String, String)> {
+        let mut tags = vec![
+            ("Event".to_string(), "muskox match".to_string()),
+            ("Black".to_string(), self.black.name.clone()),
+            ("White".to_string(), self.white.name.clone()),
+            ("GameType".to_string(), "21".to_string()),
+        ];
+
+        if let Some((color, factor)) = self.time_odds {
+            tags.push(("TimeOdds".to_string(), format!("{:?} {}", color, factor)));
+        }
+
+        if !self.material_odds.is_empty() {
+            let positions: Vec<_> = self.material_odds.iter().map(|p| p.to_string()).collect();
+            tags.push(("MaterialOdds".to_string(), positions.join(",")));
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start.fen()));
+        }
+
+        tags
+    }
+}
+
+/// Represents what an engine reported about a single move of a match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveTelemetry {
+    score: Score,
+    depth: u32,
+    nodes: u64,
+    time: Duration,
+}
+
+impl MoveTelemetry {
+    /// Returns the score of the move from black's point of view
+    #[inline]
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Returns the depth of the deepest search the engine completed
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns how many positions the engine visited
+    #[inline]
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Returns how long the engine thought about the move
+    #[inline]
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Formats the telemetry as PDN commands: `[%eval score,depth,nodes]` and the
+    /// elapsed move time as `[%emt h:mm:ss.mmm]`
+    pub fn pdn_commands(&self) -> String {
+        let millis = self.time.as_millis();
+        format!(
+            "[%eval {},{},{}][%emt {}:{:02}:{:02}.{:03}]",
+            self.score,
+            self.depth,
+            self.nodes,
+            millis / 3_600_000,
+            millis / 60_000 % 60,
+            millis / 1000 % 60,
+            millis % 1000
+        )
+    }
+
+    fn json(&self) -> String {
+        format!(
+            "\"eval\":{},\"depth\":{},\"nodes\":{},\"time_ms\":{}",
+            self.score.json(),
+            self.depth,
+            self.nodes,
+            self.time.as_millis()
+        )
+    }
+}
+
+/// Represents a game played out by a [Match](struct.Match.html)
+pub struct MatchGame {
+    tags: Vec<(String, String)>,
+    start: Bitboard,
+    actions: Vec<Action>,
+    telemetry: Vec<MoveTelemetry>,
+    result: Winner<Bitboard>,
+}
+
+impl MatchGame {
+    #[inline]
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    #[inline]
+    pub fn start(&self) -> Bitboard {
+        self.start
+    }
+
+    #[inline]
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Returns what the engines reported about each move, in the same order as
+    /// the actions
+    #[inline]
+    pub fn telemetry(&self) -> &[MoveTelemetry] {
+        &self.telemetry
+    }
+
+    #[inline]
+    pub fn result(&self) -> &Winner<Bitboard> {
+        &self.result
+    }
+
+    /// Returns the result as written in PDN, from black's point of view
+    pub fn result_text(&self) -> &'static str {
+        match self.result {
+            Winner::Player(Color::Black) => "1-0",
+            Winner::Player(Color::White) => "0-1",
+            Winner::Draw => "1/2-1/2",
+        }
+    }
+
+    /// Converts the game into a [PdnGame](../pdn/struct.PdnGame.html) with the result
+    /// tag set and the telemetry of every move in its comment
+    pub fn pdn_game(&self) -> PdnGame {
+        let mut game = PdnGame::new(self.tags.clone(), self.start, self.actions.clone());
+        game.set_tag("Result", self.result_text());
+
+        for (ply, telemetry) in self.telemetry.iter().enumerate() {
+            game.set_comment(ply, &telemetry.pdn_commands());
+        }
+
+        game
+    }
+
+    /// Generates the PDN for the game including all of the tags
+    #[inline]
+    pub fn pdn(&self) -> String {
+        self.pdn_game().pdn()
+    }
+
+    /// Writes the players, the result and every move with its telemetry as JSON
+    pub fn json(&self) -> String {
+        let tag = |name| {
+            self.tags
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+        };
+
+        let moves: Vec<_> = self
+            .actions
+            .iter()
+            .zip(self.telemetry.iter())
+            .enumerate()
+            .map(|(ply, (action, telemetry))| {
+                format!(
+                    "{{\"ply\":{},\"move\":\"{}\",{}}}",
+                    ply + 1,
+                    action,
+                    telemetry.json()
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"black\":\"{}\",\"white\":\"{}\",\"result\":\"{}\",\"moves\":[{}]}}",
+            pdn::json_escape(tag("Black").unwrap_or("")),
+            pdn::json_escape(tag("White").unwrap_or("")),
+            self.result_text(),
+            moves.join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timed(name: &str) -> Contestant {
+        Contestant::new(name, SearchConstraint::time(50).unwrap())
+    }
+
+    #[test]
+    fn time_odds_test() {
+        let game = Match::new(timed("a"), timed("b"))
+            .time_odds(Color::White, 2)
+            .unwrap();
+        match game.constraint(Color::White) {
+            SearchConstraint::Time(d) => assert_eq!(d.as_millis(), 100),
+            _ => panic!("expected a timed constraint"),
+        }
+        match game.constraint(Color::Black) {
+            SearchConstraint::Time(d) => assert_eq!(d.as_millis(), 50),
+            _ => panic!("expected a timed constraint"),
+        }
+
+        let depth = Contestant::new("c", SearchConstraint::depth(4).unwrap());
+        let err = Match::new(depth, timed("b")).time_odds(Color::Black, 2);
+        assert_eq!(
+            err.err(),
+            Some(OddsError::TimeOddsConstraintError {
+                color: Color::Black
+            })
+        );
+
+        let err = Match::new(timed("a"), timed("b")).time_odds(Color::Black, 0);
+        assert_eq!(err.err(), Some(OddsError::TimeOddsFactorError));
+    }
+
+    #[test]
+    fn material_odds_test() {
+        let game = Match::new(timed("a"), timed("b"))
+            .material_odds(&[12, 1, 12])
+            .unwrap();
+        assert_eq!(game.material_odds, vec![1, 12]);
+        assert_eq!(game.start_position().blacks(), 0x000007fe);
+        assert_eq!(game.start_position().whites(), 0xfff00000);
+
+        let err = Match::new(timed("a"), timed("b")).material_odds(&[15]);
+        assert_eq!(
+            err.err(),
+            Some(OddsError::MaterialOddsError { position: 15 })
+        );
+    }
+
+    #[test]
+    fn pdn_test() {
+        let game = Match::new(timed("a"), timed("b"))
+            .time_odds(Color::Black, 2)
+            .unwrap()
+            .material_odds(&[21])
+            .unwrap();
+        let start = game.start_position();
+
+        let game = MatchGame {
+            tags: game.tags(&start),
+            start,
+            actions: vec![
+                Action::from_movetext("11-15").unwrap(),
+                Action::from_movetext("22-18").unwrap(),
+                Action::from_movetext("15-22").unwrap(),
+            ],
+            telemetry: Vec::new(),
+            result: Winner::Draw,
+        };
+
+        assert_eq!(
+            game.pdn(),
+            "[Event \"muskox match\"]\n\
+             [Black \"a\"]\n\
+             [White \"b\"]\n\
+             [GameType \"21\"]\n\
+             [TimeOdds \"Black 2\"]\n\
+             [MaterialOdds \"21\"]\n\
+             [SetUp \"1\"]\n\
+             [FEN \"B:W22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12\"]\n\
+             [Result \"1/2-1/2\"]\n\n\
+             1. 11-15 22-18 2. 15-22 1/2-1/2"
+        );
+    }
+
+    #[test]
+    fn telemetry_test() {
+        let game = MatchGame {
+            tags: Match::new(timed("a \"b\""), timed("c")).tags(&Bitboard::default()),
+            start: Bitboard::default(),
+            actions: vec![
+                Action::from_movetext("11-15").unwrap(),
+                Action::from_movetext("22-18").unwrap(),
+            ],
+            telemetry: vec![
+                MoveTelemetry {
+                    score: Score::from(0.5),
+                    depth: 8,
+                    nodes: 123_456,
+                    time: Duration::from_millis(61_250),
+                },
+                MoveTelemetry {
+                    score: Score::NEG_INFINITY,
+                    depth: 3,
+                    nodes: 40,
+                    time: Duration::from_millis(40),
+                },
+            ],
+            result: Winner::Player(Color::White),
+        };
+
+        assert!(game.pdn().ends_with(
+            "1. 11-15 {[%eval 0.5,8,123456][%emt 0:01:01.250]} \
+             22-18 {[%eval -inf,3,40][%emt 0:00:00.040]} 0-1"
+        ));
+        assert_eq!(
+            game.json(),
+            "{\"black\":\"a \\\"b\\\"\",\"white\":\"c\",\"result\":\"0-1\",\"moves\":[\
+             {\"ply\":1,\"move\":\"11-15\",\"eval\":0.5,\"depth\":8,\"nodes\":123456,\
+             \"time_ms\":61250},\
+             {\"ply\":2,\"move\":\"22-18\",\"eval\":\"-inf\",\"depth\":3,\"nodes\":40,\
+             \"time_ms\":40}]}"
+        );
+    }
+}
@@ -27,7 +27,7 @@ use Color::*;
 
 impl Color {
     #[inline]
-    fn opponent(&self) -> Color {
+    pub(crate) fn opponent(&self) -> Color {
         match self {
             Black => White,
             White => Black,
@@ -128,6 +128,47 @@ impl Bitboard {
         Ok(())
     }
 
+    /// Returns whether playing an action hands the opponent a forced capture
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action to look past
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Action};
+    ///
+    /// let board = Bitboard::from_fen("W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,12,15").unwrap();
+    ///
+    /// assert_eq!(board.must_capture_after(Action::from_movetext("22-18").unwrap()), Ok(true));
+    /// assert_eq!(board.must_capture_after(Action::from_movetext("24-20").unwrap()), Ok(false));
+    /// ```
+    pub fn must_capture_after(&self, action: Action) -> Result<bool, ActionError> {
+        let board_p = self.take_action(action)?;
+        Ok(board_p.get_jumpers(board_p.turn) != 0)
+    }
+
+    /// Returns the captures the opponent has to choose from after an action, which
+    /// is empty if the opponent is free to make a plain move
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action to look past
+    pub fn capture_followups(&self, action: Action) -> Result<Vec<Action>, ActionError> {
+        let board_p = self.take_action(action)?;
+
+        if board_p.get_jumpers(board_p.turn) == 0 {
+            return Ok(Vec::new());
+        }
+
+        Ok(board_p
+            .generate_all_actions()
+            .iter()
+            .map(|p| *p.action())
+            .collect())
+    }
+
     /// Creates string FEN tag according to Portable Draughts Notation (PDN). Read more
     /// about the notation [here](https://en.wikipedia.org/wiki/Portable_Draughts_Notation).
     ///
@@ -416,6 +457,52 @@ impl Bitboard {
         }
     }
 
+    /// Returns the board turned 180 degrees with the colors swapped, so position
+    /// `i` of one color becomes position `31 - i` of the other. The side to move
+    /// swaps as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    ///
+    /// let board = Bitboard::default();
+    /// assert_eq!(board.flipped().fen(), "W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12");
+    /// ```
+    pub fn flipped(&self) -> Self {
+        Bitboard {
+            blacks: self.whites.reverse_bits(),
+            whites: self.blacks.reverse_bits(),
+            kings: self.kings.reverse_bits(),
+            turn: self.turn.opponent(),
+        }
+    }
+
+    /// Returns the board relative to the side to move. The side to move always
+    /// plays white and moves up the board, so positions with black to move are
+    /// [flipped](#method.flipped). Use with
+    /// [Action::relative](struct.Action.html#method.relative) to get color
+    /// symmetric inputs, for example for machine learning.
+    #[inline]
+    pub fn relative(&self) -> Self {
+        match self.turn {
+            White => *self,
+            Black => self.flipped(),
+        }
+    }
+
+    /// Returns four masks relative to the side to move: its men, its kings, the
+    /// opponent's men and the opponent's kings
+    pub fn relative_planes(&self) -> [Mask; 4] {
+        let board = self.relative();
+        [
+            board.whites & !board.kings,
+            board.whites & board.kings,
+            board.blacks & !board.kings,
+            board.blacks & board.kings,
+        ]
+    }
+
     #[inline]
     pub fn blacks(&self) -> Mask {
         self.blacks
@@ -471,7 +558,7 @@ impl Searchable for Bitboard {
         if self.turn == Black && self.get_movers(Black) == 0 && self.get_jumpers(Black) == 0 {
             return GameState::Completed(Winner::Player(White));
         }
-        if self.turn == White && self.get_movers(Black) == 0 && self.get_jumpers(Black) == 0 {
+        if self.turn == White && self.get_movers(White) == 0 && self.get_jumpers(White) == 0 {
             return GameState::Completed(Winner::Player(Black));
         }
 
@@ -832,6 +919,14 @@ impl Searchable for Bitboard {
 
         zobrist_hash
     }
+
+    #[inline]
+    fn material(&self, side: &Color) -> u32 {
+        match side {
+            Black => self.blacks.count_ones(),
+            White => self.whites.count_ones(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -867,6 +962,13 @@ mod tests {
         assert_eq!(board.whites, 0x00000404);
         assert_eq!(board.kings, 0x00000400);
         assert_eq!(board.turn, Black);
+
+        for fen in &["B:W18,33:B1", "B:W18:B0,1", "W:WK50:B1"] {
+            assert_eq!(
+                Bitboard::from_fen(fen).unwrap_err(),
+                ParseError::PositionValueError
+            );
+        }
     }
 
     #[test]
@@ -957,6 +1059,16 @@ mod tests {
             board.get_game_state(),
             GameState::Completed(Winner::Player(Black))
         );
+        // white is blocked in while black can still move
+        let board = Bitboard::from_fen("W:W29:B22,25").unwrap();
+        assert_eq!(
+            board.get_game_state(),
+            GameState::Completed(Winner::Player(Black))
+        );
+
+        // only the side to move has to be able to move
+        let board = Bitboard::from_fen("W:W5,6,10,18:B1").unwrap();
+        assert_eq!(board.get_game_state(), GameState::InProgress);
     }
 
     #[test]
@@ -1099,6 +1211,30 @@ mod tests {
         assert_eq!(board_p.kings, 0x40000000);
     }
 
+    #[test]
+    fn capture_followups_test() {
+        let board = Bitboard::default()
+            .take_action(Action::from_movetext("11-15").unwrap())
+            .unwrap();
+
+        let action = Action::from_movetext("22-18").unwrap();
+        assert_eq!(board.must_capture_after(action), Ok(true));
+        assert_eq!(
+            board.capture_followups(action),
+            Ok(vec![Action::from_movetext("15-22").unwrap()])
+        );
+
+        let action = Action::from_movetext("24-20").unwrap();
+        assert_eq!(board.must_capture_after(action), Ok(false));
+        assert_eq!(board.capture_followups(action), Ok(Vec::new()));
+
+        let action = Action::from_movetext("22-17").unwrap();
+        assert_eq!(board.must_capture_after(action), Ok(false));
+
+        let action = Action::from_movetext("15-19").unwrap();
+        assert!(board.capture_followups(action).is_err());
+    }
+
     #[test]
     fn zobrist_hashing_test() {
         // checks that the zobrist hashing is consistent with 2 different ways of making it
@@ -1127,4 +1263,28 @@ mod tests {
         assert_valid_zobrist(TEST_BOARD_2);
         assert_valid_zobrist(TEST_BOARD_3);
     }
+
+    #[test]
+    fn relative_test() {
+        let board = Bitboard::from_fen(TEST_BOARD_2).unwrap();
+        assert_eq!(board.relative(), board);
+
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+        assert_eq!(board.relative().turn, White);
+        assert_eq!(board.flipped().flipped(), board);
+        assert_eq!(
+            board.relative_planes(),
+            [0x00111000, 0x00000488, 0x00004130, 0x00420000]
+        );
+
+        // relative actions are valid on the relative board and lead to the same place
+        for board in [TEST_BOARD_1, TEST_BOARD_3, TEST_BOARD_7].iter() {
+            let board = Bitboard::from_fen(board).unwrap();
+            for node in board.generate_all_actions() {
+                let action = node.action().relative(board.turn);
+                let board_p = board.relative().take_action(action).unwrap();
+                assert_eq!(board_p, node.state().flipped());
+            }
+        }
+    }
 }
@@ -0,0 +1,601 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::analysis::Analyzer;
+use crate::board::{Action, Bitboard};
+use crate::error::CliError;
+use crate::pdn::{self, PdnGame};
+use crate::search::{Engine, EnginePool, Priority, Score, SearchConstraint};
+
+const DEFAULT_MOVETIME: &str = "1000";
+const DEFAULT_THREADS: &str = "1";
+
+// shorter searches may not finish a single iteration before time runs out
+const MIN_MOVETIME: u32 = 50;
+
+/// Runs a batch subcommand given the command line arguments after the program name
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    match args.first().map(|a| a.as_str()) {
+        Some("solve") => solve(&args[1..]),
+        Some("annotate") => annotate(&args[1..]),
+        Some(name) => Err(CliError::SubcommandError {
+            name: name.to_string(),
+        }),
+        None => Err(CliError::SubcommandError {
+            name: String::new(),
+        }),
+    }
+}
+
+/// Searches every FEN in the input (one per line) and writes the best move and
+/// score for each to stdout, in input order. Positions are written back out as
+/// the FEN of the board that was read.
+///
+/// `--threads` engines search positions side by side, sharing one transposition
+/// table. Results are flushed as soon as they are known, so everything completed
+/// before an interrupt is kept. Positions that fail to search are reported on
+/// stderr and left out.
+///
+/// ```text
+/// muskox solve --input fens.txt --movetime 500 --threads 8 --format json
+/// ```
+pub fn solve(args: &[String]) -> Result<(), CliError> {
+    let options = Options::parse(
+        args,
+        &["--input", "--movetime", "--depth", "--threads", "--format"],
+        &[],
+    )?;
+
+    let constraint = options.constraint()?;
+    let threads = options.number("--threads", DEFAULT_THREADS)?.max(1) as usize;
+    let format = match options.value("--format").unwrap_or("tsv") {
+        "tsv" => Format::Tsv,
+        "json" => Format::Json,
+        value => return Err(options.invalid("--format", value)),
+    };
+
+    if let Some(extra) = options.positional.first() {
+        return Err(CliError::OptionError {
+            option: extra.clone(),
+        });
+    }
+
+    let input = options.input()?;
+
+    let engine = Engine::new();
+    let engines = (0..threads).map(|_| engine.share_table()).collect();
+    // there is always at least one thread
+    let pool = EnginePool::new(engines).unwrap().queue_limits(0, threads);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if let Some(header) = format.header() {
+        writeln!(out, "{}", header).map_err(|source| CliError::OutputError { source })?;
+    }
+
+    let mut lines = input.lines().enumerate();
+    let mut solved = 0;
+
+    loop {
+        // read just enough positions to keep every engine busy
+        let mut batch = Vec::with_capacity(threads);
+
+        for (i, line) in lines.by_ref() {
+            let line = line.map_err(|source| CliError::InputError {
+                path: options.value("--input").unwrap_or("-").to_string(),
+                source,
+            })?;
+            let fen = line.trim();
+
+            if fen.is_empty() {
+                continue;
+            }
+
+            let board = match Bitboard::from_fen(fen) {
+                Ok(board) => board,
+                Err(err) => {
+                    eprintln!("line {}: {}", i + 1, err);
+                    continue;
+                }
+            };
+
+            // the queue is sized to fit a whole batch
+            let ticket = pool
+                .submit(0, &board, &constraint, Priority::Batch)
+                .unwrap();
+            // the parser ignores anything after the board, so the line itself
+            // could carry text that breaks the output
+            batch.push((i, board.fen(), ticket));
+
+            if batch.len() == threads {
+                break;
+            }
+        }
+
+        if batch.is_empty() {
+            break;
+        }
+
+        for (i, fen, ticket) in batch {
+            let best = match ticket.wait() {
+                Ok(results) => results.first().map(|p| (p.action(), p.score())),
+                Err(err) => {
+                    eprintln!("line {}: {}", i + 1, err);
+                    continue;
+                }
+            };
+
+            writeln!(out, "{}", format.row(&fen, best))
+                .and_then(|_| out.flush())
+                .map_err(|source| CliError::OutputError { source })?;
+
+            solved += 1;
+            eprint!("\rsolved {}", solved);
+        }
+    }
+
+    eprintln!();
+
+    Ok(())
+}
+
+/// Annotates every game in a PDN file and writes the annotated games, in input
+/// order, to an output PDN file along with a JSON summary of each game.
+///
+/// `--threads` analyzers work on games side by side, sharing one transposition
+/// table. After every game written a
+/// progress file next to the output records how far along the output is, so an
+/// interrupted run picks up where it left off with `--resume`. Games that can't be
+/// read, are not legal English draughts or fail to analyze are copied over without
+/// annotations.
+///
+/// ```text
+/// muskox annotate games.pdn --depth 18 --threads 8 --resume
+/// ```
+pub fn annotate(args: &[String]) -> Result<(), CliError> {
+    let options = Options::parse(
+        args,
+        &[
+            "--movetime",
+            "--depth",
+            "--threads",
+            "--output",
+            "--summary",
+        ],
+        &["--resume"],
+    )?;
+
+    let input = match options.positional.as_slice() {
+        [input] => input,
+        [] => {
+            return Err(CliError::MissingArgumentError {
+                argument: "input PDN file".to_string(),
+            })
+        }
+        [_, extra, ..] => {
+            return Err(CliError::OptionError {
+                option: extra.clone(),
+            })
+        }
+    };
+
+    let constraint = options.constraint()?;
+    let threads = options.number("--threads", DEFAULT_THREADS)?.max(1) as usize;
+
+    let output = match options.value("--output") {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(input).with_extension("annotated.pdn"),
+    };
+    let summary = match options.value("--summary") {
+        Some(path) => PathBuf::from(path),
+        None => output.with_extension("json"),
+    };
+    let progress = PathBuf::from(format!("{}.progress", output.display()));
+
+    let games = read_games(Path::new(input))?;
+
+    let output_error = |source| CliError::OutputError { source };
+
+    // a resumed run drops anything written after the last recorded game
+    let (mut done, mut written) = match options.switch("--resume") {
+        true => read_progress(&progress),
+        false => (0, 0),
+    };
+
+    let mut out = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&output)
+        .map_err(output_error)?;
+
+    // an output shorter than the progress claims was changed since, start over
+    if out.metadata().map_err(output_error)?.len() < written {
+        eprintln!(
+            "progress does not match {}, starting over",
+            output.display()
+        );
+        done = 0;
+        written = 0;
+    }
+
+    out.set_len(written).map_err(output_error)?;
+    out.seek(SeekFrom::End(0)).map_err(output_error)?;
+
+    let queue: VecDeque<_> = games.into_iter().enumerate().skip(done).collect();
+    let total = done + queue.len();
+    let queue = Arc::new(Mutex::new(queue));
+
+    let (results, received) = mpsc::channel();
+    let engine = Engine::new();
+
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let engine = engine.share_table();
+            let queue = queue.clone();
+            let results = results.clone();
+            thread::spawn(move || annotate_worker(engine, constraint, queue, results))
+        })
+        .collect();
+
+    // only the workers hold senders now, so the channel closes when they are done
+    drop(results);
+
+    let mut pending = BTreeMap::new();
+    let mut next = done;
+
+    for (i, pdn) in received {
+        pending.insert(i, pdn);
+
+        // games finish out of order but are written in order
+        while let Some(pdn) = pending.remove(&next) {
+            let pdn = format!("{}\n\n", pdn);
+            out.write_all(pdn.as_bytes())
+                .and_then(|_| out.flush())
+                .map_err(output_error)?;
+
+            next += 1;
+            written += pdn.len() as u64;
+
+            fs::write(&progress, format!("{}\t{}\n", next, written)).map_err(output_error)?;
+            eprint!("\rannotated {}/{}", next, total);
+        }
+    }
+
+    let crashed = workers
+        .into_iter()
+        .map(|worker| worker.join())
+        .filter(|joined| joined.is_err())
+        .count();
+
+    eprintln!();
+
+    // a worker that panicked took its game with it, so the output is missing
+    // that game and every one after it
+    if crashed > 0 || next != total {
+        return Err(CliError::WorkerError {
+            annotated: next,
+            total,
+        });
+    }
+
+    let annotated: Vec<_> = read_games(&output)?
+        .iter()
+        .map(|text| PdnGame::parse(text).ok())
+        .collect();
+    fs::write(&summary, summary_json(&annotated)).map_err(output_error)
+}
+
+fn annotate_worker(
+    engine: Engine<Bitboard>,
+    constraint: SearchConstraint,
+    queue: Arc<Mutex<VecDeque<(usize, String)>>>,
+    results: mpsc::Sender<(usize, String)>,
+) {
+    let mut analyzer = Analyzer::with_engine(engine, constraint);
+
+    loop {
+        let (i, text) = match queue.lock().unwrap().pop_front() {
+            Some(job) => job,
+            None => return,
+        };
+
+        let game = PdnGame::parse(&text).and_then(|game| game.validate().map(|_| game));
+
+        let pdn = match game {
+            Ok(game) => {
+                // a failed analysis costs the game its annotations, not the whole run
+                let analyze = || analyzer.analyze_game(&game).annotate(&game).pdn();
+                match panic::catch_unwind(AssertUnwindSafe(analyze)) {
+                    Ok(pdn) => pdn,
+                    Err(_) => {
+                        eprintln!("game {}: the analysis failed", i + 1);
+                        text
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("game {}: {}", i + 1, err);
+                text
+            }
+        };
+
+        // the writer only stops listening when it failed
+        if results.send((i, pdn)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads the text of every game in a PDN file, leaving each game to be read on
+/// its own so one bad game does not stop the rest
+fn read_games(path: &Path) -> Result<Vec<String>, CliError> {
+    let text = fs::read_to_string(path).map_err(|source| CliError::InputError {
+        path: path.display().to_string(),
+        source,
+    })?;
+
+    Ok(PdnGame::split(&text))
+}
+
+/// Reads how many games and bytes of output were completely written. A missing or
+/// unreadable progress file means starting over.
+fn read_progress(path: &Path) -> (usize, u64) {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let fields: Vec<_> = text.trim().split('\t').collect();
+
+    match fields.as_slice() {
+        [games, bytes] => match (games.parse(), bytes.parse()) {
+            (Ok(games), Ok(bytes)) => (games, bytes),
+            _ => (0, 0),
+        },
+        _ => (0, 0),
+    }
+}
+
+/// Summarizes annotated games as JSON from the tags the annotator sets. Games
+/// that could not be read have every field null.
+fn summary_json(games: &[Option<PdnGame>]) -> String {
+    let text = |game: &Option<PdnGame>, name| match game.as_ref().and_then(|g| g.tag(name)) {
+        Some(value) => format!("\"{}\"", pdn::json_escape(value)),
+        None => "null".to_string(),
+    };
+    let number = |game: &Option<PdnGame>, name| match game
+        .as_ref()
+        .and_then(|g| g.tag(name))
+        .map(|v| v.parse::<f32>())
+    {
+        Some(Ok(value)) => value.to_string(),
+        _ => "null".to_string(),
+    };
+
+    let entries: Vec<_> = games
+        .iter()
+        .enumerate()
+        .map(|(i, game)| {
+            format!(
+                "{{\"game\":{},\"black\":{},\"white\":{},\"result\":{},\"opening\":{},\
+                 \"black_accuracy\":{},\"white_accuracy\":{}}}",
+                i + 1,
+                text(game, "Black"),
+                text(game, "White"),
+                text(game, "Result"),
+                text(game, "Opening"),
+                number(game, "BlackAccuracy"),
+                number(game, "WhiteAccuracy")
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"games\":{},\"results\":[{}]}}\n",
+        games.len(),
+        entries.join(",")
+    )
+}
+
+/// Represents how results are written out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Tsv,
+    Json,
+}
+
+impl Format {
+    fn header(&self) -> Option<&'static str> {
+        match self {
+            Format::Tsv => Some("fen\tbest\tscore"),
+            Format::Json => None,
+        }
+    }
+
+    /// Formats one result. Positions without a move (the game is over) have no
+    /// best move or score.
+    fn row(&self, fen: &str, best: Option<(Action, Score)>) -> String {
+        match (self, best) {
+            (Format::Tsv, Some((action, score))) => format!("{}\t{}\t{}", fen, action, score),
+            (Format::Tsv, None) => format!("{}\t-\t-", fen),
+            (Format::Json, Some((action, score))) => format!(
+                "{{\"fen\":\"{}\",\"best\":\"{}\",\"score\":{}}}",
+                fen,
+                action,
+                score.json()
+            ),
+            (Format::Json, None) => format!("{{\"fen\":\"{}\",\"best\":null,\"score\":null}}", fen),
+        }
+    }
+}
+
+/// Command line options of the form `--name value` or `--switch`, and any other
+/// arguments in order
+struct Options {
+    values: HashMap<String, String>,
+    positional: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String], names: &[&str], switches: &[&str]) -> Result<Self, CliError> {
+        let mut values = HashMap::new();
+        let mut positional = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if switches.contains(&arg.as_str()) {
+                values.insert(arg.clone(), String::new());
+            } else if names.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| CliError::ValueError {
+                    option: arg.clone(),
+                    value: String::new(),
+                })?;
+                values.insert(arg.clone(), value.clone());
+            } else if arg.starts_with("--") {
+                return Err(CliError::OptionError {
+                    option: arg.clone(),
+                });
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+
+        Ok(Options { values, positional })
+    }
+
+    #[inline]
+    fn switch(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    #[inline]
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|v| v.as_str())
+    }
+
+    fn number(&self, name: &str, default: &str) -> Result<u32, CliError> {
+        let value = self.value(name).unwrap_or(default);
+        value.parse().map_err(|_| self.invalid(name, value))
+    }
+
+    /// Returns the search constraint from `--depth` or else `--movetime` in milliseconds
+    fn constraint(&self) -> Result<SearchConstraint, CliError> {
+        if self.value("--depth").is_some() {
+            let depth = self.number("--depth", "")?;
+            SearchConstraint::depth(depth).map_err(|_| self.invalid("--depth", &depth.to_string()))
+        } else {
+            let movetime = self.number("--movetime", DEFAULT_MOVETIME)?;
+            if movetime < MIN_MOVETIME {
+                return Err(self.invalid("--movetime", &movetime.to_string()));
+            }
+            SearchConstraint::time(movetime)
+                .map_err(|_| self.invalid("--movetime", &movetime.to_string()))
+        }
+    }
+
+    /// Opens `--input`, reading from stdin if it is missing or `-`
+    fn input(&self) -> Result<Box<dyn BufRead>, CliError> {
+        match self.value("--input") {
+            None | Some("-") => Ok(Box::new(BufReader::new(io::stdin()))),
+            Some(path) => {
+                let file = File::open(path).map_err(|source| CliError::InputError {
+                    path: path.to_string(),
+                    source,
+                })?;
+                Ok(Box::new(BufReader::new(file)))
+            }
+        }
+    }
+
+    #[inline]
+    fn invalid(&self, option: &str, value: &str) -> CliError {
+        CliError::ValueError {
+            option: option.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn options_test() {
+        let options = Options::parse(
+            &args("games.pdn --movetime 500 --threads 8 --resume"),
+            &["--movetime", "--threads"],
+            &["--resume"],
+        )
+        .unwrap();
+        assert_eq!(options.positional, vec!["games.pdn"]);
+        assert!(options.switch("--resume"));
+        assert_eq!(options.number("--threads", "1").unwrap(), 8);
+        assert_eq!(options.number("--depth", "3").unwrap(), 3);
+        match options.constraint().unwrap() {
+            SearchConstraint::Time(d) => assert_eq!(d.as_millis(), 500),
+            _ => panic!("expected a timed constraint"),
+        }
+
+        let options = Options::parse(&args("--movetime 1"), &["--movetime"], &[]).unwrap();
+        assert!(options.constraint().is_err());
+
+        assert!(Options::parse(&args("--bogus 1"), &["--movetime"], &[]).is_err());
+        assert!(Options::parse(&args("--movetime"), &["--movetime"], &[]).is_err());
+    }
+
+    #[test]
+    fn format_test() {
+        let fen = "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29";
+        let best = Some((Action::from_movetext("22-17").unwrap(), Score::from(1.5)));
+
+        assert_eq!(Format::Tsv.row(fen, best), format!("{}\t22-17\t1.5", fen));
+        assert_eq!(
+            Format::Json.row(fen, best),
+            format!("{{\"fen\":\"{}\",\"best\":\"22-17\",\"score\":1.5}}", fen)
+        );
+        assert_eq!(
+            Format::Json.row(fen, Some((best.unwrap().0, Score::NEG_INFINITY))),
+            format!(
+                "{{\"fen\":\"{}\",\"best\":\"22-17\",\"score\":\"-inf\"}}",
+                fen
+            )
+        );
+        assert_eq!(
+            Format::Json.row(fen, None),
+            format!("{{\"fen\":\"{}\",\"best\":null,\"score\":null}}", fen)
+        );
+    }
+
+    #[test]
+    fn summary_test() {
+        let games: Vec<_> = PdnGame::split(
+            "[Black \"a\"]\n[White \"b\"]\n[Result \"1-0\"]\n\
+             [Opening \"Dyke\"]\n[BlackAccuracy \"91.5\"]\n[WhiteAccuracy \"80.0\"]\n\
+             1. 11-15 22-17 2. 15-19 1-0\n\
+             [Black \"d\"]\n1. 9-13 *\n\
+             [Black \"e\"]\n1. 9-40 *",
+        )
+        .iter()
+        .map(|text| PdnGame::parse(text).ok())
+        .collect();
+
+        assert_eq!(
+            summary_json(&games),
+            "{\"games\":3,\"results\":[\
+             {\"game\":1,\"black\":\"a\",\"white\":\"b\",\"result\":\"1-0\",\"opening\":\"Dyke\",\
+             \"black_accuracy\":91.5,\"white_accuracy\":80},\
+             {\"game\":2,\"black\":\"d\",\"white\":null,\"result\":null,\"opening\":null,\
+             \"black_accuracy\":null,\"white_accuracy\":null},\
+             {\"game\":3,\"black\":null,\"white\":null,\"result\":null,\"opening\":null,\
+             \"black_accuracy\":null,\"white_accuracy\":null}]}\n"
+        );
+    }
+}
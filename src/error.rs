@@ -2,6 +2,7 @@ use nom::error::{VerboseError, VerboseErrorKind::Context};
 use snafu::Snafu;
 
 use crate::board::Color;
+use crate::search::Priority;
 
 #[derive(Debug, PartialEq, Snafu)]
 pub enum ActionError {
@@ -32,7 +33,99 @@ pub enum ActionError {
     NeedMoreJumpingError,
 }
 
+#[derive(Debug, PartialEq, Snafu)]
+pub enum OddsError {
+    #[snafu(display("Position {} does not hold a man in the starting position", position))]
+    MaterialOddsError { position: u8 },
+
+    #[snafu(display("Time odds factor must be at least 1!"))]
+    TimeOddsFactorError,
+
+    #[snafu(display("Time odds need a timed search constraint for {:?}", color))]
+    TimeOddsConstraintError { color: Color },
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum PoolError {
+    #[snafu(display("Too many {:?} searches are queued, try again later!", priority))]
+    QueueFullError { priority: Priority },
+
+    #[snafu(display("The engine pool shut down before the search finished!"))]
+    PoolClosedError,
+
+    #[snafu(display("The engine panicked during the search!"))]
+    SearchPanicError,
+
+    #[snafu(display("An engine pool needs at least one engine!"))]
+    NoEnginesError,
+}
+
 #[derive(Debug, Snafu)]
+pub enum CliError {
+    #[snafu(display("Unknown subcommand {}!", name))]
+    SubcommandError { name: String },
+
+    #[snafu(display("Unknown option {}!", option))]
+    OptionError { option: String },
+
+    #[snafu(display("Invalid value {:?} for option {}!", value, option))]
+    ValueError { option: String, value: String },
+
+    #[snafu(display("Couldn't read {}: {}", path, source))]
+    InputError {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Couldn't write results: {}", source))]
+    OutputError { source: std::io::Error },
+
+    #[snafu(display("Missing {}!", argument))]
+    MissingArgumentError { argument: String },
+
+    #[snafu(display(
+        "Only {} of {} games were annotated, a worker crashed!",
+        annotated,
+        total
+    ))]
+    WorkerError { annotated: usize, total: usize },
+}
+
+#[derive(Debug, Snafu)]
+pub enum RepertoireError {
+    #[snafu(display("Line {} could not be read: {}", line + 1, source))]
+    LineParseError { line: usize, source: ImportError },
+
+    #[snafu(display("Move {} of line {} is invalid: {}", ply + 1, line + 1, source))]
+    LineActionError {
+        line: usize,
+        ply: usize,
+        source: ActionError,
+    },
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum ImportError {
+    #[snafu(display("Game type {} is not English draughts (21)!", game_type))]
+    GameTypeError { game_type: String },
+
+    #[snafu(display("Move {} is illegal: {}", ply + 1, source))]
+    IllegalMoveError { ply: usize, source: ActionError },
+
+    #[snafu(display("Square {} of the FEN is not numbered 1 - 32!", square))]
+    FenSquareError { square: u32 },
+
+    #[snafu(display("Square {} of move {} is not numbered 1 - 32!", square, ply + 1))]
+    MoveSquareError { ply: usize, square: u32 },
+
+    #[snafu(display("Couldn't read the FEN: {}", source))]
+    FenError { source: ParseError },
+
+    #[snafu(display("Couldn't read move {}: {}", ply + 1, source))]
+    MovetextError { ply: usize, source: ParseError },
+}
+
+#[derive(Debug, PartialEq, Snafu)]
 pub enum ParseError {
     // for board below
     #[snafu(display("Invalid color letter (W and B are valid)!"))]
@@ -72,12 +165,21 @@ pub enum ParseError {
 
     #[snafu(display("Invalid command!"))]
     InvalidCommand,
+
+    // for feature planes
+    #[snafu(display("Feature planes do not describe a valid board!"))]
+    PlanesError,
+
+    // for saved quiz progress
+    #[snafu(display("Invalid quiz progress on line {}!", line))]
+    ProgressError { line: usize },
 }
 
 impl<T> From<nom::Err<VerboseError<T>>> for ParseError {
     fn from(err: nom::Err<VerboseError<T>>) -> Self {
         let errors = match err {
             nom::Err::Error(VerboseError { errors }) => errors,
+            nom::Err::Failure(VerboseError { errors }) => errors,
             _ => vec![],
         };
 
@@ -1,19 +1,30 @@
+pub mod analysis;
 pub mod app;
+pub mod arena;
+pub mod cli;
 pub mod error;
+pub mod opening;
+pub mod pdn;
+pub mod quiz;
+pub mod repertoire;
 
 mod action;
 mod bitboard;
 mod evaluation;
 mod parse;
+mod planes;
+mod position;
 mod zobrist;
 
 pub mod search {
     mod engine;
+    mod pool;
     mod score;
     mod searchable;
     mod tt;
 
     pub use engine::*;
+    pub use pool::*;
     pub use score::*;
     pub use searchable::*;
 }
@@ -21,4 +32,6 @@ pub mod search {
 pub mod board {
     pub use super::action::*;
     pub use super::bitboard::*;
+    pub use super::planes::*;
+    pub use super::position::*;
 }
@@ -1,5 +1,18 @@
-use muskox::app;
+use std::env;
+use std::process;
+
+use muskox::{app, cli};
 
 fn main() {
-    app::run();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // without a subcommand muskox is interactive
+    if args.is_empty() {
+        app::run();
+    }
+
+    if let Err(err) = cli::run(&args) {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
 }
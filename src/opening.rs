@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::board::{Action, Bitboard};
+use crate::parse;
+use crate::search::Searchable;
+
+// traditional names of common lines from the starting position. longer lines are
+// refinements of the shorter lines they start with.
+const OPENINGS: [(&str, &str); 22] = [
+    ("Edinburgh", "9-13"),
+    ("Double Corner", "9-14"),
+    ("Denny", "10-14"),
+    ("Kelso", "10-15"),
+    ("Bristol", "11-16"),
+    ("Dundee", "12-16"),
+    ("Switcher", "11-15 21-17"),
+    ("Single Corner", "11-15 22-18"),
+    ("Cross", "11-15 23-18"),
+    ("Second Double Corner", "11-15 24-19"),
+    ("Dyke", "11-15 22-17 15-19"),
+    ("Maid of the Mill", "11-15 22-17 8-11 17-13 15-18"),
+    ("Ayrshire Lassie", "11-15 24-20 8-11"),
+    ("Will o' the Wisp", "11-15 23-19 9-13"),
+    ("Whilter", "11-15 23-19 9-14 27-23"),
+    ("Fife", "11-15 23-19 9-14 22-17 5-9"),
+    ("Souter", "11-15 23-19 9-14 22-17 6-9"),
+    ("Old Fourteenth", "11-15 23-19 8-11 22-17 4-8"),
+    ("Laird and Lady", "11-15 23-19 8-11 22-17 9-13"),
+    ("Glasgow", "11-15 23-19 8-11 22-17 11-16"),
+    ("Alma", "11-15 23-19 8-11 22-17 3-8"),
+    ("Bristol Cross", "11-16 23-18"),
+];
+
+lazy_static! {
+    static ref OPENING_NAMES: Vec<OpeningName> = OPENINGS
+        .iter()
+        .map(|&(name, movetext)| OpeningName {
+            name,
+            // the lines above are known to be valid
+            actions: parse::pdn_movetext(movetext, Bitboard::default()).unwrap(),
+        })
+        .collect();
+
+    // positions reached by each named line, so move orders that transpose into a
+    // line get its name too
+    static ref POSITIONS: HashMap<Bitboard, usize> = OPENING_NAMES
+        .iter()
+        .enumerate()
+        .map(|(i, opening)| {
+            let board = opening
+                .actions
+                .iter()
+                .fold(Bitboard::default(), |board, &a| board.take_action(a).unwrap());
+            (board, i)
+        })
+        .collect();
+}
+
+/// Represents a traditional name for a line from the starting position
+#[derive(Debug, PartialEq)]
+pub struct OpeningName {
+    name: &'static str,
+    actions: Vec<Action>,
+}
+
+impl OpeningName {
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the moves of the line the name was given to
+    #[inline]
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+}
+
+/// Returns every opening in the dictionary
+pub fn openings() -> &'static [OpeningName] {
+    &OPENING_NAMES
+}
+
+/// Names the opening of a game played from the starting position. The most specific
+/// name wins, and lines are matched by the positions they reach so games that get
+/// there through a different move order are named too. Returns `None` if the game
+/// never reaches a named position.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Action;
+/// use muskox::opening;
+///
+/// let moves = |m: &[&str]| -> Vec<Action> { m.iter().map(|m| Action::from_movetext(m).unwrap()).collect() };
+///
+/// let opening = opening::classify(&moves(&["11-15", "22-18", "15-22", "25-18"])).unwrap();
+/// assert_eq!(opening.name(), "Single Corner");
+///
+/// // transposes into the Old Fourteenth
+/// let opening = opening::classify(&moves(&["11-15", "22-17", "8-11", "23-19", "4-8"])).unwrap();
+/// assert_eq!(opening.name(), "Old Fourteenth");
+/// ```
+pub fn classify(actions: &[Action]) -> Option<&'static OpeningName> {
+    let mut board = Bitboard::default();
+    let mut found = None;
+
+    for &action in actions {
+        board = match board.take_action(action) {
+            Ok(board_p) => board_p,
+            Err(_) => break,
+        };
+
+        if let Some(&i) = POSITIONS.get(&board) {
+            found = Some(&OPENING_NAMES[i]);
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(movetext: &str) -> Vec<Action> {
+        parse::pdn_movetext(movetext, Bitboard::default()).unwrap()
+    }
+
+    #[test]
+    fn openings_test() {
+        // every line is valid and reaches its own position
+        for (i, opening) in openings().iter().enumerate() {
+            assert_eq!(classify(opening.actions()), Some(&OPENING_NAMES[i]));
+        }
+        assert_eq!(POSITIONS.len(), OPENINGS.len());
+    }
+
+    #[test]
+    fn classify_test() {
+        let name = |movetext| classify(&moves(movetext)).map(|o| o.name());
+
+        assert_eq!(name("11-15 23-19 8-11 22-17"), None);
+        assert_eq!(name("11-15 23-19 8-11 22-17 11-16 24-20"), Some("Glasgow"));
+        assert_eq!(name("11-15 22-17 8-11 23-19 9-13"), Some("Laird and Lady"));
+        assert_eq!(name("11-15 23-18 15-22"), Some("Cross"));
+        assert_eq!(name("9-14 22-18"), Some("Double Corner"));
+        assert_eq!(name(""), None);
+    }
+}
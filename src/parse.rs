@@ -2,7 +2,7 @@ use nom::{
     bytes::complete::{tag, take, take_while},
     character::complete::digit1,
     combinator::{map, map_res},
-    error::{context, VerboseError},
+    error::{context, VerboseError, VerboseErrorKind},
     multi::separated_list1,
     sequence::tuple,
     IResult,
@@ -10,9 +10,9 @@ use nom::{
 use num_traits::PrimInt;
 
 use crate::app::Command;
-use crate::board::{Action, Bitboard, Color};
-use crate::error::ParseError;
-use crate::search::SearchConstraint;
+use crate::board::{Action, ActionType, Bitboard, Color};
+use crate::error::{ImportError, ParseError};
+use crate::search::{SearchConstraint, Searchable};
 
 // try to condense these functions except for stuff taht is too large or reused..
 // convert the match statements to nom's switch macro
@@ -23,16 +23,23 @@ fn from_decimal<T: PrimInt>(input: &str) -> Result<T, T::FromStrRadixErr> {
     T::from_str_radix(input, 10)
 }
 
+// a failure rather than an error so list parsers stop instead of backtracking
+fn position_failure(input: &str) -> nom::Err<VerboseError<&str>> {
+    nom::Err::Failure(VerboseError {
+        errors: vec![(input, VerboseErrorKind::Context("position"))],
+    })
+}
+
 // everything below is for parsing the action
 
 fn position_primary(input: &str) -> Res<&str, u8> {
-    let (input, position) = context("position", map_res(digit1, from_decimal::<u8>))(input)?;
+    let (rest, position) = context("position", map_res(digit1, from_decimal::<u8>))(input)?;
 
     if !(1..=32).contains(&position) {
-        panic!("another unhandled error!");
+        return Err(position_failure(input));
     }
 
-    Ok((input, position))
+    Ok((rest, position))
 }
 
 pub(crate) fn action_primary(input: &str) -> Res<&str, Action> {
@@ -131,12 +138,15 @@ fn piece_primary(input: &str) -> Res<&str, (u32, bool)> {
         return Ok((input, (0, false)));
     }
 
-    let (input, position) = context("digit", map_res(digit1, from_decimal::<u32>))(input)?;
+    let (rest, position) = context("digit", map_res(digit1, from_decimal::<u32>))(input)?;
+
+    if !(1..=32).contains(&position) {
+        return Err(position_failure(input));
+    }
 
-    // need to make sure the position is less than 32
     let mask = 1 << (position - 1);
 
-    Ok((input, (mask, is_king)))
+    Ok((rest, (mask, is_king)))
 }
 
 fn side_primary(input: &str) -> Res<&str, (Color, u32, u32)> {
@@ -183,3 +193,121 @@ pub(crate) fn board_fen_primary(input: &str) -> Res<&str, Bitboard> {
 
     Ok((input, board))
 }
+
+// everything below is for parsing pdn games
+
+const PDN_RESULTS: [&str; 7] = ["1-0", "0-1", "1/2-1/2", "2-0", "0-2", "1-1", "*"];
+
+/// Reads a pdn tag pair such as `[Event "Casual"]`. Quotes and backslashes in
+/// the value are escaped with a backslash.
+pub(crate) fn pdn_tag(input: &str) -> Option<(String, String)> {
+    let input = input.trim().strip_prefix('[')?.strip_suffix(']')?;
+
+    let mut parts = input.splitn(2, ' ');
+    let name = parts.next()?.trim();
+    let value = parts.next()?.trim();
+    let value = value.strip_prefix('"').unwrap_or(value);
+    let value = value.strip_suffix('"').unwrap_or(value);
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+
+    Some((name.to_string(), unescaped))
+}
+
+/// Reads all of the actions from pdn movetext played from `start`, skipping move
+/// numbers, results, move strength annotations, comments and variations
+pub(crate) fn pdn_movetext(input: &str, start: Bitboard) -> Result<Vec<Action>, ImportError> {
+    // throw away comments and variations first
+    let mut stripped = String::with_capacity(input.len());
+    let mut nesting = 0;
+    for c in input.chars() {
+        match c {
+            '{' | '(' => nesting += 1,
+            '}' | ')' if nesting > 0 => nesting -= 1,
+            _ if nesting == 0 => stripped.push(c),
+            _ => (),
+        }
+    }
+
+    let mut actions = Vec::new();
+
+    // only known for as long as every move so far was legal
+    let mut board = Some(start);
+
+    for token in stripped.split_whitespace() {
+        if PDN_RESULTS.contains(&token) {
+            continue;
+        }
+
+        // move numbers can be glued onto the move like "1.11-15"
+        let token = token.rsplit('.').next().unwrap_or("");
+        let token = token.trim_end_matches(&['!', '?'][..]);
+        if token.is_empty() {
+            continue;
+        }
+
+        let ply = actions.len();
+
+        let squares = token
+            .split(&['-', 'x'][..])
+            .map(from_decimal::<u32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ImportError::MovetextError {
+                ply,
+                source: ParseError::InvalidAction,
+            })?;
+
+        // checked here since the action parser panics on these
+        if let Some(&square) = squares.iter().find(|s| !(1..=32).contains(*s)) {
+            return Err(ImportError::MoveSquareError { ply, square });
+        }
+
+        // captures may be written with only their first and last square, like
+        // 9x27, in which case the squares in between come from the legal jumps
+        let capture = match (board, squares.as_slice()) {
+            (Some(board), &[source, destination]) if token.contains('x') => {
+                resolve_capture(&board, source, destination)
+            }
+            _ => None,
+        };
+
+        let action = match capture {
+            Some(action) => action,
+            None => {
+                let positions = squares.into_iter().map(|s| s as u8).collect();
+                Action::from_vec(positions)
+                    .map_err(|source| ImportError::MovetextError { ply, source })?
+            }
+        };
+
+        board = board.and_then(|b| b.take_action(action).ok());
+        actions.push(action);
+    }
+
+    Ok(actions)
+}
+
+/// Finds the only legal jump from `source` to `destination`, both numbered 1 - 32
+fn resolve_capture(board: &Bitboard, source: u32, destination: u32) -> Option<Action> {
+    let mut jumps = board
+        .generate_all_actions()
+        .into_iter()
+        .map(|p| *p.action())
+        .filter(|a| {
+            a.action_type() == ActionType::Jump
+                && u32::from(a.source()) + 1 == source
+                && u32::from(a.destination()) + 1 == destination
+        });
+
+    match (jumps.next(), jumps.next()) {
+        (Some(action), None) => Some(action),
+        _ => None,
+    }
+}
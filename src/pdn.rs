@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use crate::board::{Action, Bitboard, Color};
+use crate::error::{ImportError, ParseError};
+use crate::parse;
+use crate::search::Searchable;
+
+/// Represents a single game in Portable Draughts Notation (PDN). Read more about
+/// the notation [here](https://en.wikipedia.org/wiki/Portable_Draughts_Notation).
+///
+/// Only the tags, the starting position and the main line are kept. Comments and
+/// variations are skipped when reading, but both can be attached to moves before
+/// writing the game back out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdnGame {
+    tags: Vec<(String, String)>,
+    start: Bitboard,
+    actions: Vec<Action>,
+    comments: HashMap<usize, String>,
+    variations: HashMap<usize, Vec<Action>>,
+}
+
+impl PdnGame {
+    pub fn new(tags: Vec<(String, String)>, start: Bitboard, actions: Vec<Action>) -> Self {
+        PdnGame {
+            tags,
+            start,
+            actions,
+            comments: HashMap::new(),
+            variations: HashMap::new(),
+        }
+    }
+
+    /// Reads every game in a PDN file. Games are separated by their tag sections.
+    /// Any game that can't be read fails the whole file, use [split](#method.split)
+    /// and [parse](#method.parse) to read the games one at a time instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::pdn::PdnGame;
+    ///
+    /// let games = PdnGame::parse_all("[Event \"a\"]\n1. 11-15 23-19 *\n[Event \"b\"]\n1. 9-13 *").unwrap();
+    /// assert_eq!(games.len(), 2);
+    /// assert_eq!(games[1].tag("Event"), Some("b"));
+    /// assert_eq!(games[1].actions()[0].movetext(), "9-13");
+    /// ```
+    pub fn parse_all(input: &str) -> Result<Vec<Self>, ImportError> {
+        PdnGame::split(input)
+            .iter()
+            .map(|text| PdnGame::parse(text))
+            .collect()
+    }
+
+    /// Splits a PDN file into the text of each of its games. A tag after some
+    /// movetext starts the next game.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::pdn::PdnGame;
+    ///
+    /// let texts = PdnGame::split("[Event \"a\"]\n1. 11-15 *\n\n[Event \"b\"]\n1. 9-13 99-0 *");
+    /// assert_eq!(texts, vec!["[Event \"a\"]\n1. 11-15 *", "[Event \"b\"]\n1. 9-13 99-0 *"]);
+    ///
+    /// assert!(PdnGame::parse(&texts[0]).is_ok());
+    /// assert!(PdnGame::parse(&texts[1]).is_err());
+    /// ```
+    pub fn split(input: &str) -> Vec<String> {
+        let mut texts = Vec::new();
+
+        let mut lines: Vec<&str> = Vec::new();
+        let mut has_movetext = false;
+
+        for line in input.lines() {
+            match parse::pdn_tag(line) {
+                Some(_) if has_movetext => {
+                    texts.push(lines.join("\n").trim().to_string());
+                    lines.clear();
+                    has_movetext = false;
+                }
+                Some(_) => (),
+                None => has_movetext |= !line.trim().is_empty(),
+            }
+            lines.push(line);
+        }
+
+        let last = lines.join("\n");
+        if !last.trim().is_empty() {
+            texts.push(last.trim().to_string());
+        }
+
+        texts
+    }
+
+    /// Reads a single game, such as one returned by [split](#method.split). The
+    /// tags of every game in the text would be read as the tags of one game.
+    ///
+    /// Captures written with only their first and last squares, like `9x27`, are
+    /// read as the legal jump between them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::pdn::PdnGame;
+    ///
+    /// let game = PdnGame::parse("[FEN \"B:W14,15,22,23:B9\"]\n1. 9x27 *").unwrap();
+    /// assert_eq!(game.actions()[0].movetext(), "9-18-27");
+    /// ```
+    pub fn parse(input: &str) -> Result<Self, ImportError> {
+        let mut tags = Vec::new();
+        let mut movetext = String::new();
+
+        for line in input.lines() {
+            match parse::pdn_tag(line) {
+                Some(tag) => tags.push(tag),
+                None => {
+                    movetext.push_str(line);
+                    movetext.push(' ');
+                }
+            }
+        }
+
+        PdnGame::from_parts(tags, &movetext)
+    }
+
+    /// Reads every game in a PDN file like [parse_all](#method.parse_all) and
+    /// [validates](#method.validate) each one, so only legal English draughts
+    /// games are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::error::ImportError;
+    /// use muskox::pdn::PdnGame;
+    ///
+    /// assert!(PdnGame::parse_all_validated("1. 11-15 23-19 *").is_ok());
+    ///
+    /// let result = PdnGame::parse_all_validated("[FEN \"B:W18,40:B1\"]\n1. 1-5 *");
+    /// assert_eq!(result, Err(ImportError::FenSquareError { square: 40 }));
+    ///
+    /// let result = PdnGame::parse_all_validated("1. 11-15 23-19 2. 8-0 *");
+    /// assert_eq!(result, Err(ImportError::MoveSquareError { ply: 2, square: 0 }));
+    /// ```
+    pub fn parse_all_validated(input: &str) -> Result<Vec<Self>, ImportError> {
+        let games = PdnGame::parse_all(input)?;
+
+        for game in &games {
+            game.validate()?;
+        }
+
+        Ok(games)
+    }
+
+    fn from_parts(tags: Vec<(String, String)>, movetext: &str) -> Result<Self, ImportError> {
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Bitboard::from_fen(fen).map_err(|source| match source {
+                // the board parser does not say which square was out of range
+                ParseError::PositionValueError => match bad_square(fen) {
+                    Some(square) => ImportError::FenSquareError { square },
+                    None => ImportError::FenError { source },
+                },
+                source => ImportError::FenError { source },
+            })?,
+            None => Bitboard::default(),
+        };
+
+        let actions = parse::pdn_movetext(movetext, start)?;
+
+        Ok(PdnGame::new(tags, start, actions))
+    }
+
+    /// Checks that the game is English draughts and that every move is legal.
+    /// Reading a game only checks that the positions are numbered 1 - 32, so games
+    /// of other variants or with another numbering can still read as nonsense.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::error::ImportError;
+    /// use muskox::pdn::PdnGame;
+    ///
+    /// let games = PdnGame::parse_all("[GameType \"21\"]\n1. 11-15 23-19 *").unwrap();
+    /// assert_eq!(games[0].validate(), Ok(()));
+    ///
+    /// let games = PdnGame::parse_all("1. 11-15 15-19 *").unwrap();
+    /// assert!(matches!(games[0].validate(), Err(ImportError::IllegalMoveError { ply: 1, .. })));
+    ///
+    /// let games = PdnGame::parse_all("[GameType \"20\"]\n1. 32-28 *").unwrap();
+    /// assert!(games[0].validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), ImportError> {
+        // the game type may be followed by the board layout after a comma
+        if let Some(game_type) = self.tag("GameType") {
+            if game_type.split(',').next().map(|t| t.trim()) != Some("21") {
+                return Err(ImportError::GameTypeError {
+                    game_type: game_type.to_string(),
+                });
+            }
+        }
+
+        let mut board = self.start;
+
+        for (ply, &action) in self.actions.iter().enumerate() {
+            board = board
+                .take_action(action)
+                .map_err(|source| ImportError::IllegalMoveError { ply, source })?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Returns the value of the first tag with a particular name
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of a tag, replacing it if it already exists
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    #[inline]
+    pub fn start(&self) -> Bitboard {
+        self.start
+    }
+
+    #[inline]
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Attaches a comment after the action at index `ply`
+    pub fn set_comment(&mut self, ply: usize, comment: &str) {
+        self.comments.insert(ply, comment.to_string());
+    }
+
+    #[inline]
+    pub fn comment(&self, ply: usize) -> Option<&str> {
+        self.comments.get(&ply).map(|c| c.as_str())
+    }
+
+    /// Attaches a variation that replaces the action at index `ply`. The variation
+    /// is written in parentheses right after that action.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Action, Bitboard};
+    /// use muskox::pdn::PdnGame;
+    ///
+    /// let moves = |m: &[&str]| m.iter().map(|m| Action::from_movetext(m).unwrap()).collect();
+    ///
+    /// let mut game = PdnGame::new(Vec::new(), Bitboard::default(), moves(&["11-15", "23-19", "8-11"]));
+    /// game.set_variation(0, &moves(&["9-13"]));
+    /// game.set_variation(1, &moves(&["22-18", "15-22"]));
+    /// assert_eq!(game.pdn(), "\n1. 11-15 (1. 9-13) 1... 23-19 (1... 22-18 2. 15-22) 2. 8-11 *");
+    /// ```
+    pub fn set_variation(&mut self, ply: usize, actions: &[Action]) {
+        self.variations.insert(ply, actions.to_vec());
+    }
+
+    #[inline]
+    pub fn variation(&self, ply: usize) -> Option<&[Action]> {
+        self.variations.get(&ply).map(|v| v.as_slice())
+    }
+
+    /// Generates the PDN for the game. The movetext is terminated with the value
+    /// of the `Result` tag or `*` if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::pdn::PdnGame;
+    ///
+    /// let game = PdnGame::parse("[Event \"the \\\"big\\\" one\"]\n1. 11-15 *").unwrap();
+    /// assert_eq!(game.tag("Event"), Some("the \"big\" one"));
+    /// assert_eq!(game.pdn(), "[Event \"the \\\"big\\\" one\"]\n\n1. 11-15 *");
+    /// ```
+    pub fn pdn(&self) -> String {
+        let mut out = String::new();
+
+        // tag values escape quotes and backslashes the same way json strings do
+        for (name, value) in self.tags.iter() {
+            out.push_str(&format!("[{} \"{}\"]\n", name, json_escape(value)));
+        }
+        out.push('\n');
+
+        // black moves first, so white to move means the first move number is skipped
+        let offset = match self.start.turn() {
+            Color::Black => 0,
+            Color::White => 1,
+        };
+
+        for (i, action) in self.actions.iter().enumerate() {
+            // the main line picks up again after a variation
+            let resumed = i == 0 || self.variations.contains_key(&(i - 1));
+
+            out.push_str(&move_number(i + offset, resumed));
+            out.push_str(&action.movetext());
+            out.push(' ');
+
+            if let Some(comment) = self.comments.get(&i) {
+                out.push_str(&format!("{{{}}} ", comment));
+            }
+
+            if let Some(variation) = self.variations.get(&i) {
+                let mut movetexts = Vec::with_capacity(variation.len());
+                for (j, action) in variation.iter().enumerate() {
+                    movetexts.push(format!("{}{}", move_number(i + j + offset, j == 0), action));
+                }
+                out.push_str(&format!("({}) ", movetexts.join(" ")));
+            }
+        }
+
+        out.push_str(self.tag("Result").unwrap_or("*"));
+
+        out
+    }
+}
+
+/// Finds the first number in a FEN that is not a square 1 - 32
+fn bad_square(fen: &str) -> Option<u32> {
+    fen.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse().ok())
+        .find(|n| !(1..=32).contains(n))
+}
+
+/// Returns the move number written before the action at `ply`. White's moves only
+/// get one when they start or resume a line.
+fn move_number(ply: usize, first: bool) -> String {
+    match (ply % 2, first) {
+        (0, _) => format!("{}. ", ply / 2 + 1),
+        (_, true) => format!("{}... ", ply / 2 + 1),
+        _ => String::new(),
+    }
+}
+
+/// Escapes text, such as tag values, to be written inside a JSON string
+pub(crate) fn json_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
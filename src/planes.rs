@@ -0,0 +1,253 @@
+use crate::board::{Bitboard, Color, Position};
+use crate::error::ParseError;
+
+const SQUARES: usize = 64;
+
+/// Represents the channel layout of the feature planes produced by
+/// [Bitboard::to_planes](struct.Bitboard.html#method.to_planes).
+///
+/// Every plane is a full 8x8 board in row major order starting from the top left
+/// corner, the way [pretty](struct.Bitboard.html#method.pretty) prints it, so
+/// position 1 is at index 1 and position 5 is at index 8. Light squares are
+/// always 0 in piece planes. The channels are, in order:
+///
+/// * `Absolute`: black men, black kings, white men, white kings
+/// * `Relative`: the mover's men, the mover's kings, the opponent's men and the
+///   opponent's kings, all on the [relative board](struct.Bitboard.html#method.relative)
+/// * `RelativeWithCounters`: the four relative planes followed by a plane filled
+///   with the repetition count of the position and a plane filled with the number
+///   of plies since the last capture or man move. The counters come from the game,
+///   so only [Position::to_planes](struct.Position.html#method.to_planes) fills
+///   them in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaneLayout {
+    Absolute,
+    Relative,
+    RelativeWithCounters,
+}
+
+impl PlaneLayout {
+    #[inline]
+    pub fn num_planes(&self) -> usize {
+        match self {
+            PlaneLayout::Absolute | PlaneLayout::Relative => 4,
+            PlaneLayout::RelativeWithCounters => 6,
+        }
+    }
+
+    /// Returns the total number of values in the planes
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.num_planes() * SQUARES
+    }
+}
+
+/// Returns the index within a plane of a position on the board
+#[inline]
+fn cell(position: u32) -> usize {
+    let row = position / 4;
+    let col = 2 * (position % 4) + 1 - row % 2;
+    (row * 8 + col) as usize
+}
+
+impl Bitboard {
+    /// Returns the board as feature planes for neural networks. See
+    /// [PlaneLayout](enum.PlaneLayout.html) for the channel ordering. A board on its
+    /// own has no history, so its counter planes are 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The channels to produce
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, PlaneLayout};
+    ///
+    /// let planes = Bitboard::default().to_planes(PlaneLayout::Absolute);
+    /// assert_eq!(planes.len(), 256);
+    /// assert_eq!(planes[1], 1.); // black man on position 1
+    /// assert_eq!(planes[128 + 62], 1.); // white man on position 32
+    /// ```
+    pub fn to_planes(&self, layout: PlaneLayout) -> Vec<f32> {
+        self.planes(layout, 0, 0)
+    }
+
+    fn planes(&self, layout: PlaneLayout, repetitions: u32, quiet_plies: u32) -> Vec<f32> {
+        let masks = match layout {
+            PlaneLayout::Absolute => [
+                self.blacks() & !self.kings(),
+                self.blacks() & self.kings(),
+                self.whites() & !self.kings(),
+                self.whites() & self.kings(),
+            ],
+            _ => self.relative_planes(),
+        };
+
+        let mut planes = vec![0.; layout.size()];
+
+        for (i, mask) in masks.iter().enumerate() {
+            for position in 0..32 {
+                if (mask >> position) & 1 == 1 {
+                    planes[i * SQUARES + cell(position)] = 1.;
+                }
+            }
+        }
+
+        if layout == PlaneLayout::RelativeWithCounters {
+            for value in planes[4 * SQUARES..5 * SQUARES].iter_mut() {
+                *value = repetitions as f32;
+            }
+            for value in planes[5 * SQUARES..].iter_mut() {
+                *value = quiet_plies as f32;
+            }
+        }
+
+        planes
+    }
+
+    /// Rebuilds a board from feature planes, mostly for debugging. The side to
+    /// move is not part of the planes, so it has to be given. Counter planes are
+    /// ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `planes` - Planes produced by [to_planes](#method.to_planes)
+    /// * `layout` - The layout the planes were produced with
+    /// * `turn` - The side to move
+    pub fn from_planes(
+        planes: &[f32],
+        layout: PlaneLayout,
+        turn: Color,
+    ) -> Result<Self, ParseError> {
+        if planes.len() != layout.size() {
+            return Err(ParseError::PlanesError);
+        }
+
+        let mut masks = [0_u32; 4];
+
+        for (i, mask) in masks.iter_mut().enumerate() {
+            for (index, &value) in planes[i * SQUARES..(i + 1) * SQUARES].iter().enumerate() {
+                if value < 0.5 {
+                    continue;
+                }
+
+                let (row, col) = (index / 8, index % 8);
+
+                // pieces can only stand on dark squares
+                if (row + col) % 2 == 0 {
+                    return Err(ParseError::PlanesError);
+                }
+
+                *mask |= 1 << (row * 4 + col / 2);
+            }
+        }
+
+        // no square can hold more than one piece
+        if masks.iter().map(|m| m.count_ones()).sum::<u32>()
+            != masks.iter().fold(0, |acc, m| acc | m).count_ones()
+        {
+            return Err(ParseError::PlanesError);
+        }
+
+        let first = masks[0] | masks[1];
+        let second = masks[2] | masks[3];
+        let kings = masks[1] | masks[3];
+
+        let board = match layout {
+            PlaneLayout::Absolute => Bitboard::new(first, second, kings, turn),
+            _ => {
+                let board = Bitboard::new(second, first, kings, Color::White);
+                match turn {
+                    Color::White => board,
+                    Color::Black => board.flipped(),
+                }
+            }
+        };
+
+        Ok(board)
+    }
+}
+
+impl Position {
+    /// Returns the position as feature planes for neural networks, with the
+    /// counter planes taken from [repetitions](#method.repetitions) and
+    /// [quiet_plies](#method.quiet_plies). See [PlaneLayout](enum.PlaneLayout.html)
+    /// for the channel ordering.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The channels to produce
+    pub fn to_planes(&self, layout: PlaneLayout) -> Vec<f32> {
+        self.board()
+            .planes(layout, self.repetitions(), self.quiet_plies())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Action;
+    use crate::search::Searchable;
+
+    const TEST_BOARD_1: &'static str = "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29";
+    const TEST_BOARD_2: &'static str = "W:W9,K11,19,K26,27,30:B15,22,25,K32";
+
+    #[test]
+    fn cell_test() {
+        assert_eq!(cell(0), 1);
+        assert_eq!(cell(3), 7);
+        assert_eq!(cell(4), 8);
+        assert_eq!(cell(31), 62);
+    }
+
+    #[test]
+    fn planes_round_trip_test() {
+        let layouts = [
+            PlaneLayout::Absolute,
+            PlaneLayout::Relative,
+            PlaneLayout::RelativeWithCounters,
+        ];
+
+        for fen in [TEST_BOARD_1, TEST_BOARD_2].iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            for &layout in layouts.iter() {
+                let planes = board.to_planes(layout);
+                assert_eq!(planes.len(), layout.size());
+                let board_p = Bitboard::from_planes(&planes, layout, board.turn()).unwrap();
+                assert_eq!(board_p, board);
+            }
+        }
+    }
+
+    #[test]
+    fn planes_test() {
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+
+        let planes = board.to_planes(PlaneLayout::RelativeWithCounters);
+        assert!(planes[4 * SQUARES..].iter().all(|&v| v == 0.));
+
+        // two kings step away and back, repeating the position once
+        let mut position = Position::new(board);
+        for movetext in ["25-21", "10-6", "21-25", "6-10"].iter() {
+            position
+                .make(Action::from_movetext(movetext).unwrap())
+                .unwrap();
+        }
+        let planes = position.to_planes(PlaneLayout::RelativeWithCounters);
+        assert_eq!(
+            planes[..4 * SQUARES],
+            board.to_planes(PlaneLayout::Relative)[..]
+        );
+        assert_eq!(planes[4 * SQUARES], 1.);
+        assert_eq!(planes[6 * SQUARES - 1], 4.);
+
+        // black is to move, so its king on 29 is the mover's king on 4
+        assert_eq!(planes[SQUARES + cell(3)], 1.);
+
+        let mut planes = board.to_planes(PlaneLayout::Absolute);
+        planes[0] = 1.;
+        assert!(Bitboard::from_planes(&planes, PlaneLayout::Absolute, Color::Black).is_err());
+        assert!(Bitboard::from_planes(&planes[1..], PlaneLayout::Absolute, Color::Black).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use crate::board::{Action, ActionType, Bitboard, Color};
+use crate::error::ActionError;
+use crate::search::{GameState, Searchable};
+use crate::zobrist;
+
+/// A board that is changed in place by making and unmaking actions, with the
+/// zobrist hash kept up to date and a record of earlier positions for detecting
+/// repetitions.
+///
+/// This is the stable interface for writing a custom search on top of the rules.
+/// Every [make](#method.make) hands back a [Token](struct.Token.html) that undoes
+/// it, and tokens have to be given back to [unmake](#method.unmake) in the
+/// reverse order they were made in.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::{Action, Position};
+///
+/// let mut position = Position::default();
+/// let mut actions = Vec::new();
+/// position.legal_actions_into(&mut actions);
+/// assert_eq!(actions.len(), 7);
+///
+/// let hash = position.hash();
+/// let token = position.make(Action::from_movetext("11-15").unwrap()).unwrap();
+/// assert_ne!(position.hash(), hash);
+///
+/// position.unmake(token);
+/// assert_eq!(position.hash(), hash);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Position {
+    board: Bitboard,
+    hash: u64,
+    // hashes of every earlier position in the game
+    history: Vec<u64>,
+    // index into the history of the first position after the last capture or man move
+    reversible_from: usize,
+}
+
+/// Undoes a single [make](struct.Position.html#method.make). Tokens cannot be
+/// copied, so each one is given back at most once.
+#[derive(Debug)]
+pub struct Token {
+    board: Bitboard,
+    hash: u64,
+    ply: usize,
+    reversible_from: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::new(Bitboard::default())
+    }
+}
+
+impl From<Bitboard> for Position {
+    fn from(board: Bitboard) -> Self {
+        Position::new(board)
+    }
+}
+
+impl Position {
+    pub fn new(board: Bitboard) -> Self {
+        Position {
+            board,
+            hash: board.zobrist_hash(),
+            history: Vec::new(),
+            reversible_from: 0,
+        }
+    }
+
+    #[inline]
+    pub fn board(&self) -> Bitboard {
+        self.board
+    }
+
+    #[inline]
+    pub fn turn(&self) -> Color {
+        self.board.turn()
+    }
+
+    /// Returns the zobrist hash of the current position
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the number of actions made since the position was created
+    #[inline]
+    pub fn ply(&self) -> usize {
+        self.history.len()
+    }
+
+    #[inline]
+    pub fn game_state(&self) -> GameState<Bitboard> {
+        self.board.get_game_state()
+    }
+
+    /// Clears `out` and fills it with every legal action. Reusing the same vector
+    /// keeps the actions themselves from needing a new allocation every node, but
+    /// the move generator still builds its own list of actions and boards first.
+    pub fn legal_actions_into(&self, out: &mut Vec<Action>) {
+        out.clear();
+        out.extend(
+            self.board
+                .generate_all_actions()
+                .iter()
+                .map(|p| *p.action()),
+        );
+    }
+
+    /// Plays an action, returning the token that undoes it. The position is left
+    /// untouched if the action is invalid.
+    pub fn make(&mut self, action: Action) -> Result<Token, ActionError> {
+        let board_p = self.board.take_action(action)?;
+
+        let token = Token {
+            board: self.board,
+            hash: self.hash,
+            ply: self.history.len(),
+            reversible_from: self.reversible_from,
+        };
+
+        // captures and man moves can never be undone, so no earlier position can repeat
+        let is_king = (self.board.kings() >> action.source()) & 1 == 1;
+        if action.action_type() == ActionType::Jump || !is_king {
+            self.reversible_from = self.history.len() + 1;
+        }
+
+        self.history.push(self.hash);
+        self.hash ^= hash_diff(&self.board, &board_p);
+        self.board = board_p;
+
+        Ok(token)
+    }
+
+    /// Takes back the action the token was made for
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token is not from the most recent make that is still in effect
+    pub fn unmake(&mut self, token: Token) {
+        assert_eq!(
+            token.ply + 1,
+            self.history.len(),
+            "tokens must be unmade in the reverse order they were made"
+        );
+
+        self.history.pop();
+        self.board = token.board;
+        self.hash = token.hash;
+        self.reversible_from = token.reversible_from;
+    }
+
+    /// Returns how many times the current position came up before. Only positions
+    /// since the last capture or man move are counted, since none before can repeat.
+    pub fn repetitions(&self) -> u32 {
+        self.history[self.reversible_from..]
+            .iter()
+            .filter(|&&hash| hash == self.hash)
+            .count() as u32
+    }
+
+    /// Returns the number of plies since the last capture or man move
+    #[inline]
+    pub fn quiet_plies(&self) -> u32 {
+        (self.history.len() - self.reversible_from) as u32
+    }
+}
+
+/// Returns how the zobrist hash changes between two boards, only looking at the
+/// squares that differ
+fn hash_diff(before: &Bitboard, after: &Bitboard) -> u64 {
+    let piece_hash = |board: &Bitboard, position: u8| {
+        let mask = 1 << position;
+        let is_king = board.kings() & mask != 0;
+
+        if board.blacks() & mask != 0 {
+            zobrist::get_position_hash(position, Color::Black, is_king)
+        } else if board.whites() & mask != 0 {
+            zobrist::get_position_hash(position, Color::White, is_king)
+        } else {
+            0
+        }
+    };
+
+    let mut changed = (before.blacks() ^ after.blacks())
+        | (before.whites() ^ after.whites())
+        | (before.kings() ^ after.kings());
+
+    let mut diff = 0;
+    while changed != 0 {
+        let position = changed.trailing_zeros() as u8;
+        changed &= changed - 1;
+        diff ^= piece_hash(before, position) ^ piece_hash(after, position);
+    }
+
+    if before.turn() != after.turn() {
+        diff ^= zobrist::get_turn_hash();
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_BOARD_1: &'static str = "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29";
+
+    fn make(position: &mut Position, movetext: &str) -> Token {
+        position
+            .make(Action::from_movetext(movetext).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn make_unmake_test() {
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+        let mut position = Position::new(board);
+
+        let token_1 = make(&mut position, "22-17");
+        assert_eq!(position.hash(), position.board().zobrist_hash());
+        assert_eq!(position.ply(), 1);
+
+        let token_2 = make(&mut position, "15-11");
+        assert!(position
+            .make(Action::from_movetext("1-5").unwrap())
+            .is_err());
+        assert_eq!(position.ply(), 2);
+
+        position.unmake(token_2);
+        position.unmake(token_1);
+        assert_eq!(position.board(), board);
+        assert_eq!(position.hash(), board.zobrist_hash());
+
+        let mut actions = vec![Action::from_movetext("1-5").unwrap()];
+        position.legal_actions_into(&mut actions);
+        assert_eq!(actions.len(), board.generate_all_actions().len());
+    }
+
+    #[test]
+    fn hash_test() {
+        // the first legal action each ply plays through captures and crowning
+        let mut position = Position::default();
+        let mut actions = Vec::new();
+
+        for _ in 0..80 {
+            position.legal_actions_into(&mut actions);
+            let action = match actions.first() {
+                Some(&action) => action,
+                None => break,
+            };
+
+            position.make(action).unwrap();
+            assert_eq!(position.hash(), position.board().zobrist_hash());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn unmake_order_test() {
+        let mut position = Position::new(Bitboard::from_fen(TEST_BOARD_1).unwrap());
+        let token_1 = make(&mut position, "22-17");
+        let _token_2 = make(&mut position, "15-11");
+        position.unmake(token_1);
+    }
+
+    #[test]
+    fn repetitions_test() {
+        let mut position = Position::new(Bitboard::from_fen(TEST_BOARD_1).unwrap());
+
+        // shuffle kings back and forth
+        for _ in 0..2 {
+            make(&mut position, "22-17");
+            make(&mut position, "15-11");
+            make(&mut position, "17-22");
+            make(&mut position, "11-15");
+        }
+        assert_eq!(position.repetitions(), 2);
+        assert_eq!(position.quiet_plies(), 8);
+
+        let token = make(&mut position, "16-19");
+        assert_eq!(position.repetitions(), 0);
+        assert_eq!(position.quiet_plies(), 0);
+
+        position.unmake(token);
+        assert_eq!(position.repetitions(), 2);
+        assert_eq!(position.quiet_plies(), 8);
+    }
+}
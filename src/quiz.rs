@@ -0,0 +1,434 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use crate::board::{Action, Bitboard, Color};
+use crate::error::ParseError;
+use crate::pdn::PdnGame;
+use crate::search::{Engine, Optim, Score, SearchConstraint, Searchable, Side};
+
+const DEFAULT_DEPTH: u32 = 8;
+const DEFAULT_MARGIN: f32 = 1.;
+
+const START_EASE: f32 = 2.5;
+const MIN_EASE: f32 = 1.3;
+
+/// Represents a position where a player went wrong in one of their games
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    board: Bitboard,
+    played: Action,
+    best: Vec<Action>,
+    loss: Score,
+}
+
+impl Question {
+    #[inline]
+    pub fn board(&self) -> Bitboard {
+        self.board
+    }
+
+    /// Returns the move the player chose in the game
+    #[inline]
+    pub fn played(&self) -> Action {
+        self.played
+    }
+
+    /// Returns every move that scored as well as the engine's top move, best first
+    #[inline]
+    pub fn best(&self) -> &[Action] {
+        &self.best
+    }
+
+    /// Returns how much worse the played move was than the best move in men
+    #[inline]
+    pub fn loss(&self) -> Score {
+        self.loss
+    }
+
+    /// Returns whether the answer is one of the best moves
+    #[inline]
+    pub fn check(&self, answer: Action) -> bool {
+        self.best.contains(&answer)
+    }
+}
+
+/// Picks the `n` positions from a player's games where the engine finds their move
+/// was the worst, searching each position to a fixed depth.
+///
+/// The player is matched against the `Black` and `White` tags of each game.
+pub fn from_player_games(db: &[PdnGame], player: &str, n: usize) -> Vec<Question> {
+    let mut engine = Engine::new();
+    let constraint = SearchConstraint::depth(DEFAULT_DEPTH).unwrap();
+
+    from_player_games_with(
+        db,
+        player,
+        n,
+        &mut engine,
+        &constraint,
+        Score::from(DEFAULT_MARGIN),
+    )
+}
+
+/// Same as [from_player_games](fn.from_player_games.html), but with a given engine,
+/// search constraint and the smallest loss that counts as an error
+pub fn from_player_games_with(
+    db: &[PdnGame],
+    player: &str,
+    n: usize,
+    engine: &mut Engine<Bitboard>,
+    constraint: &SearchConstraint,
+    margin: Score,
+) -> Vec<Question> {
+    let mut seen = HashSet::new();
+    let mut questions = Vec::new();
+
+    for game in db {
+        let color = if game.tag("Black") == Some(player) {
+            Color::Black
+        } else if game.tag("White") == Some(player) {
+            Color::White
+        } else {
+            continue;
+        };
+
+        let mut board = game.start();
+
+        for &action in game.actions() {
+            let board_p = match board.take_action(action) {
+                Ok(board_p) => board_p,
+                Err(_) => break, // the rest of a broken game is meaningless
+            };
+
+            if board.turn() == color && seen.insert(board) {
+                if let Some(question) = find_error(engine, constraint, margin, board, action) {
+                    questions.push(question);
+                }
+            }
+
+            board = board_p;
+        }
+    }
+
+    // biggest mistakes first
+    questions.sort_by_key(|q| Reverse(q.loss));
+    questions.truncate(n);
+
+    questions
+}
+
+fn find_error(
+    engine: &mut Engine<Bitboard>,
+    constraint: &SearchConstraint,
+    margin: Score,
+    board: Bitboard,
+    played: Action,
+) -> Option<Question> {
+    let results = engine.search(&board, constraint);
+
+    let best_score = results.first()?.score();
+    let played_score = results.iter().find(|p| p.action() == played)?.score();
+
+    let loss = match board.turn().optim() {
+        Optim::Max => best_score - played_score,
+        Optim::Min => played_score - best_score,
+    };
+
+    if loss <= margin {
+        return None;
+    }
+
+    // a position can have several moves that are equally good
+    let best = results
+        .iter()
+        .take_while(|p| p.score() == best_score)
+        .map(|p| p.action())
+        .collect();
+
+    Some(Question {
+        board,
+        played,
+        best,
+        loss,
+    })
+}
+
+/// Represents a single question in a [Schedule](struct.Schedule.html) along with
+/// how well it has been answered so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    board: Bitboard,
+    best: Vec<Action>,
+    interval: u32,
+    ease: f32,
+    streak: u32,
+    due: u64,
+    correct: u32,
+    attempts: u32,
+}
+
+impl Card {
+    #[inline]
+    pub fn board(&self) -> Bitboard {
+        self.board
+    }
+
+    /// Returns every move that is accepted as an answer
+    #[inline]
+    pub fn best(&self) -> &[Action] {
+        &self.best
+    }
+
+    /// Returns the day the card should be shown next
+    #[inline]
+    pub fn due(&self) -> u64 {
+        self.due
+    }
+
+    #[inline]
+    pub fn correct(&self) -> u32 {
+        self.correct
+    }
+
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+/// A spaced repetition schedule over quiz questions.
+///
+/// Cards answered correctly come back after growing intervals while cards
+/// answered wrong come back the next day, roughly following SM-2. Days are plain
+/// counters chosen by the caller, for example days since the epoch.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::{Action, Bitboard};
+/// use muskox::quiz::Schedule;
+///
+/// let mut schedule = Schedule::new();
+/// schedule.add_position(Bitboard::default(), &[Action::from_movetext("11-15").unwrap()], 0);
+///
+/// assert_eq!(schedule.due(0), vec![0]);
+/// assert_eq!(schedule.answer(0, Action::from_movetext("11-15").unwrap(), 0), Some(true));
+/// assert!(schedule.due(0).is_empty());
+///
+/// let saved = schedule.serialize();
+/// assert_eq!(Schedule::deserialize(&saved).unwrap(), schedule);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schedule {
+    cards: Vec<Card>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule { cards: Vec::new() }
+    }
+
+    /// Creates a schedule with every question due on `today`
+    pub fn from_questions(questions: &[Question], today: u64) -> Self {
+        let mut schedule = Schedule::new();
+        for question in questions {
+            schedule.add_position(question.board, &question.best, today);
+        }
+        schedule
+    }
+
+    /// Adds a position to the schedule unless it is already in it. Any of the
+    /// `best` moves counts as a correct answer.
+    pub fn add_position(&mut self, board: Bitboard, best: &[Action], today: u64) {
+        if self.cards.iter().any(|c| c.board == board) {
+            return;
+        }
+
+        self.cards.push(Card {
+            board,
+            best: best.to_vec(),
+            interval: 0,
+            ease: START_EASE,
+            streak: 0,
+            due: today,
+            correct: 0,
+            attempts: 0,
+        });
+    }
+
+    #[inline]
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    /// Returns the indices of all the cards that are due on `today`
+    pub fn due(&self, today: u64) -> Vec<usize> {
+        self.cards
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.due <= today)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Records an answer for a card and schedules it again. Returns whether the
+    /// answer was correct, or `None` if there is no card at `index`.
+    pub fn answer(&mut self, index: usize, answer: Action, today: u64) -> Option<bool> {
+        let card = self.cards.get_mut(index)?;
+        let correct = card.best.contains(&answer);
+
+        card.attempts += 1;
+
+        if correct {
+            card.correct += 1;
+            card.streak += 1;
+            card.interval = match card.streak {
+                1 => 1,
+                2 => 6,
+                _ => (card.interval as f32 * card.ease).round() as u32,
+            };
+            card.ease += 0.1;
+        } else {
+            card.streak = 0;
+            card.interval = 1;
+            card.ease = (card.ease - 0.2).max(MIN_EASE);
+        }
+
+        card.due = today + card.interval as u64;
+
+        Some(correct)
+    }
+
+    /// Writes the progress out with one tab separated card per line. The best
+    /// moves are separated by commas.
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        for card in self.cards.iter() {
+            out.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                card.board.fen(),
+                card.best
+                    .iter()
+                    .map(|a| a.movetext())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                card.interval,
+                card.ease,
+                card.streak,
+                card.due,
+                card.correct,
+                card.attempts
+            ));
+        }
+
+        out
+    }
+
+    /// Reads progress written by [serialize](#method.serialize)
+    pub fn deserialize(input: &str) -> Result<Self, ParseError> {
+        let mut cards = Vec::new();
+
+        for (i, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let error = || ParseError::ProgressError { line: i + 1 };
+
+            let fields: Vec<_> = line.split('\t').collect();
+            if fields.len() != 8 {
+                return Err(error());
+            }
+
+            let best = fields[1]
+                .split(',')
+                .map(Action::from_movetext)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error())?;
+
+            cards.push(Card {
+                board: Bitboard::from_fen(fields[0]).map_err(|_| error())?,
+                best,
+                interval: fields[2].parse().map_err(|_| error())?,
+                ease: fields[3].parse().map_err(|_| error())?,
+                streak: fields[4].parse().map_err(|_| error())?,
+                due: fields[5].parse().map_err(|_| error())?,
+                correct: fields[6].parse().map_err(|_| error())?,
+                attempts: fields[7].parse().map_err(|_| error())?,
+            });
+        }
+
+        Ok(Schedule { cards })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_BOARD_1: &'static str = "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29";
+
+    #[test]
+    fn schedule_answer_test() {
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+        let best = Action::from_movetext("22-17").unwrap();
+        let also_best = Action::from_movetext("22-18").unwrap();
+        let wrong = Action::from_movetext("16-19").unwrap();
+
+        let mut schedule = Schedule::new();
+        schedule.add_position(board, &[best, also_best], 10);
+        schedule.add_position(board, &[wrong], 10);
+        assert_eq!(schedule.cards().len(), 1);
+
+        assert_eq!(schedule.answer(0, best, 10), Some(true));
+        assert_eq!(schedule.cards()[0].due(), 11);
+        assert_eq!(schedule.answer(0, also_best, 11), Some(true));
+        assert_eq!(schedule.cards()[0].due(), 17);
+        assert_eq!(schedule.answer(0, best, 17), Some(true));
+        assert_eq!(schedule.cards()[0].due(), 33);
+
+        assert_eq!(schedule.answer(0, wrong, 33), Some(false));
+        assert_eq!(schedule.answer(1, wrong, 33), None);
+        assert_eq!(schedule.cards()[0].due(), 34);
+        assert_eq!(schedule.cards()[0].correct(), 3);
+        assert_eq!(schedule.cards()[0].attempts(), 4);
+        assert_eq!(schedule.due(33), vec![]);
+        assert_eq!(schedule.due(34), vec![0]);
+    }
+
+    #[test]
+    fn schedule_serialize_test() {
+        let mut schedule = Schedule::new();
+        schedule.add_position(
+            Bitboard::from_fen(TEST_BOARD_1).unwrap(),
+            &[
+                Action::from_movetext("22-17").unwrap(),
+                Action::from_movetext("22-18").unwrap(),
+            ],
+            3,
+        );
+        schedule.answer(0, Action::from_movetext("16-19").unwrap(), 3);
+
+        let saved = schedule.serialize();
+        assert_eq!(
+            saved,
+            "B:WK10,K15,18,24,27,28:B12,16,20,K22,K25,K29\t22-17,22-18\t1\t2.3\t0\t4\t0\t1\n"
+        );
+        assert_eq!(Schedule::deserialize(&saved).unwrap(), schedule);
+
+        for saved in &[
+            "B:W18:B12\t22-17\t1",
+            "B:W18,40:B12\t22-17\t1\t2.5\t0\t4\t0\t1",
+            "B:W18:B12\t0-5\t1\t2.5\t0\t4\t0\t1",
+            "B:W18:B12\t\t1\t2.5\t0\t4\t0\t1",
+            "B:W18:B12\t22-17,\t1\t2.5\t0\t4\t0\t1",
+            "B:W18:B12\t22-99999999999\t1\t2.5\t0\t4\t0\t1",
+        ] {
+            match Schedule::deserialize(saved) {
+                Err(ParseError::ProgressError { line: 1 }) => (),
+                _ => panic!("expected a progress error for {:?}", saved),
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis;
+use crate::board::{Action, Bitboard, Color};
+use crate::error::RepertoireError;
+use crate::parse;
+use crate::pdn::PdnGame;
+use crate::search::{Engine, Score, SearchConstraint, Searchable};
+
+/// Represents a problem found in a repertoire. Each issue points at the line and
+/// the ply (index of the position within the line) where it first shows up.
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    /// A line ends with the repertoire's side to move and no other line covers the position
+    Hole { line: usize, ply: usize },
+
+    /// Several lines transpose into the same position but prescribe different moves
+    Conflict {
+        line: usize,
+        ply: usize,
+        actions: Vec<Action>,
+    },
+
+    /// The engine found a move better than the prescribed one by more than the margin
+    Refuted {
+        line: usize,
+        ply: usize,
+        action: Action,
+        best: Action,
+        loss: Score,
+    },
+}
+
+/// A personal opening repertoire for one side built from a set of preferred lines.
+///
+/// Every line is replayed from its starting position and each position along the
+/// way is stored in a book, keyed by the position rather than the move order, so
+/// lines that transpose into each other share their entries.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::{Bitboard, Color};
+/// use muskox::repertoire::Repertoire;
+///
+/// let mut repertoire = Repertoire::new(Color::Black);
+/// repertoire.add_line("1. 11-15 23-19 2. 8-11 22-17").unwrap();
+/// repertoire.add_line("11-15 22-17 8-11 23-19 9-13").unwrap();
+///
+/// let book = repertoire.probe(&Bitboard::default()).unwrap();
+/// assert_eq!(book[0].movetext(), "11-15");
+///
+/// // the first line transposes into the second, so it has no holes
+/// assert!(repertoire.check_transpositions().is_empty());
+/// ```
+pub struct Repertoire {
+    color: Color,
+    lines: Vec<PdnGame>,
+    book: HashMap<Bitboard, Vec<Action>>,
+}
+
+impl Repertoire {
+    pub fn new(color: Color) -> Self {
+        Repertoire {
+            color,
+            lines: Vec::new(),
+            book: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    #[inline]
+    pub fn lines(&self) -> &[PdnGame] {
+        &self.lines
+    }
+
+    /// Adds a line written as movetext from the default starting position.
+    /// Move numbers, results and comments are allowed and skipped.
+    pub fn add_line(&mut self, movetext: &str) -> Result<(), RepertoireError> {
+        let actions = parse::pdn_movetext(movetext, Bitboard::default()).map_err(|source| {
+            RepertoireError::LineParseError {
+                line: self.lines.len(),
+                source,
+            }
+        })?;
+
+        self.add_game(PdnGame::new(Vec::new(), Bitboard::default(), actions))
+    }
+
+    /// Adds every game in a PDN file as a line
+    pub fn add_pdn(&mut self, pdn: &str) -> Result<(), RepertoireError> {
+        let games = PdnGame::parse_all(pdn).map_err(|source| RepertoireError::LineParseError {
+            line: self.lines.len(),
+            source,
+        })?;
+
+        for game in games {
+            self.add_game(game)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a single game as a line. The whole line is checked before anything
+    /// is added to the book.
+    pub fn add_game(&mut self, game: PdnGame) -> Result<(), RepertoireError> {
+        let line = self.lines.len();
+
+        let mut board = game.start();
+        let mut entries = Vec::with_capacity(game.actions().len());
+
+        for (ply, &action) in game.actions().iter().enumerate() {
+            let board_p = board
+                .take_action(action)
+                .map_err(|source| RepertoireError::LineActionError { line, ply, source })?;
+            entries.push((board, action));
+            board = board_p;
+        }
+
+        for (board, action) in entries {
+            let actions = self.book.entry(board).or_default();
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+
+        self.lines.push(game);
+
+        Ok(())
+    }
+
+    /// Returns the moves the repertoire knows for a position. For positions where
+    /// the opponent is to move these are the replies the repertoire prepares for.
+    pub fn probe(&self, board: &Bitboard) -> Option<&[Action]> {
+        self.book.get(board).map(|actions| actions.as_slice())
+    }
+
+    /// Finds holes and conflicting moves that only show up once transpositions
+    /// between the lines are taken into account
+    pub fn check_transpositions(&self) -> Vec<Issue> {
+        let mut seen = HashSet::new();
+        let mut issues = Vec::new();
+
+        for (line, game) in self.lines.iter().enumerate() {
+            for (ply, board) in Repertoire::positions(game).iter().enumerate() {
+                if board.turn() != self.color || !seen.insert(*board) {
+                    continue;
+                }
+
+                match self.book.get(board) {
+                    Some(actions) if actions.len() > 1 => issues.push(Issue::Conflict {
+                        line,
+                        ply,
+                        actions: actions.clone(),
+                    }),
+                    Some(_) => (),
+                    // only the end of a line can be missing from the book
+                    None => {
+                        if !board.generate_all_actions().is_empty() {
+                            issues.push(Issue::Hole { line, ply });
+                        }
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Searches every prescribed move and reports the ones where the engine finds
+    /// a move that is better for the repertoire's side by more than `margin`
+    pub fn check_refutations(
+        &self,
+        engine: &mut Engine<Bitboard>,
+        constraint: &SearchConstraint,
+        margin: Score,
+    ) -> Vec<Issue> {
+        let mut seen = HashSet::new();
+        let mut issues = Vec::new();
+
+        for (line, game) in self.lines.iter().enumerate() {
+            let positions = Repertoire::positions(game);
+
+            for (ply, (board, &action)) in positions.iter().zip(game.actions()).enumerate() {
+                if board.turn() != self.color || !seen.insert((*board, action)) {
+                    continue;
+                }
+
+                let analysis = match analysis::analyze_move(engine, constraint, board, action) {
+                    Some(analysis) => analysis,
+                    None => continue,
+                };
+
+                let loss = analysis.loss();
+
+                if loss > margin {
+                    issues.push(Issue::Refuted {
+                        line,
+                        ply,
+                        action,
+                        best: analysis.best(),
+                        loss,
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Exports the book as one position per line: the FEN followed by the moves
+    /// the repertoire plays there
+    pub fn book_text(&self) -> String {
+        let mut seen = HashSet::new();
+        let mut out = String::new();
+
+        for game in self.lines.iter() {
+            for board in Repertoire::positions(game) {
+                if board.turn() != self.color || !seen.insert(board) {
+                    continue;
+                }
+
+                if let Some(actions) = self.book.get(&board) {
+                    let movetexts: Vec<_> = actions.iter().map(|a| a.movetext()).collect();
+                    out.push_str(&format!("{} {}\n", board.fen(), movetexts.join(",")));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Exports every line as a PDN game with the issues attached as comments
+    pub fn study(&self, issues: &[Issue]) -> String {
+        let mut games: Vec<_> = self.lines.to_vec();
+
+        for (i, game) in games.iter_mut().enumerate() {
+            if game.tag("Event").is_none() {
+                game.set_tag(
+                    "Event",
+                    &format!("{:?} repertoire line {}", self.color, i + 1),
+                );
+            }
+        }
+
+        for issue in issues {
+            let (line, ply, comment) = match issue {
+                Issue::Hole { line, ply } => (*line, *ply, "hole: no reply prepared".to_string()),
+                Issue::Conflict { line, ply, actions } => {
+                    let movetexts: Vec<_> = actions.iter().map(|a| a.movetext()).collect();
+                    let comment = format!(
+                        "transposition: the repertoire plays {}",
+                        movetexts.join(", ")
+                    );
+                    (*line, *ply, comment)
+                }
+                Issue::Refuted {
+                    line,
+                    ply,
+                    best,
+                    loss,
+                    ..
+                } => (
+                    *line,
+                    *ply,
+                    format!("refuted: {} is better by {}", best, loss),
+                ),
+            };
+
+            let game = match games.get_mut(line) {
+                Some(game) if !game.actions().is_empty() => game,
+                _ => continue,
+            };
+
+            // positions at the end of a line are commented on the last move
+            let at = ply.min(game.actions().len() - 1);
+
+            let comment = match game.comment(at) {
+                Some(existing) => format!("{}; {}", existing, comment),
+                None => comment,
+            };
+            game.set_comment(at, &comment);
+        }
+
+        let pdns: Vec<_> = games.iter().map(|g| g.pdn()).collect();
+        pdns.join("\n\n")
+    }
+
+    /// Returns every position in a line including the one after the last move
+    fn positions(game: &PdnGame) -> Vec<Bitboard> {
+        let mut board = game.start();
+        let mut positions = vec![board];
+
+        for &action in game.actions() {
+            // lines are checked when they are added
+            board = board.take_action(action).unwrap();
+            positions.push(board);
+        }
+
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE_1: &'static str = "11-15 23-19 8-11 22-17";
+    const LINE_2: &'static str = "1. 11-15 22-17 2. 8-11 23-19 *";
+    const LINE_3: &'static str = "11-15 23-19 8-11 22-17 9-13";
+    const LINE_4: &'static str = "11-15 22-17 8-11 23-19 10-14";
+
+    #[test]
+    fn add_line_test() {
+        let mut repertoire = Repertoire::new(Color::Black);
+        repertoire.add_line(LINE_1).unwrap();
+
+        let board = Bitboard::default();
+        assert_eq!(
+            repertoire.probe(&board),
+            Some(&[Action::from_movetext("11-15").unwrap()][..])
+        );
+
+        match repertoire.add_line("11-15 11-15") {
+            Err(RepertoireError::LineActionError {
+                line: 1, ply: 1, ..
+            }) => (),
+            _ => panic!("expected the second move to be invalid"),
+        }
+        assert_eq!(repertoire.lines().len(), 1);
+    }
+
+    #[test]
+    fn check_transpositions_test() {
+        let mut repertoire = Repertoire::new(Color::Black);
+        repertoire.add_line(LINE_1).unwrap();
+        repertoire.add_line(LINE_2).unwrap();
+        assert_eq!(
+            repertoire.check_transpositions(),
+            vec![Issue::Hole { line: 0, ply: 4 }]
+        );
+
+        repertoire.add_line(LINE_3).unwrap();
+        assert_eq!(repertoire.check_transpositions(), vec![]);
+
+        repertoire.add_line(LINE_4).unwrap();
+        assert_eq!(
+            repertoire.check_transpositions(),
+            vec![Issue::Conflict {
+                line: 0,
+                ply: 4,
+                actions: vec![
+                    Action::from_movetext("9-13").unwrap(),
+                    Action::from_movetext("10-14").unwrap()
+                ]
+            }]
+        );
+
+        // white has nothing prepared after black's last moves
+        let mut repertoire = Repertoire::new(Color::White);
+        repertoire.add_line(LINE_3).unwrap();
+        assert_eq!(
+            repertoire.check_transpositions(),
+            vec![Issue::Hole { line: 0, ply: 5 }]
+        );
+    }
+
+    #[test]
+    fn study_test() {
+        let mut repertoire = Repertoire::new(Color::Black);
+        repertoire.add_line(LINE_1).unwrap();
+
+        let issues = repertoire.check_transpositions();
+        assert_eq!(
+            repertoire.study(&issues),
+            "[Event \"Black repertoire line 1\"]\n\n\
+             1. 11-15 23-19 2. 8-11 22-17 {hole: no reply prepared} *"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use std::cmp::{self, Reverse};
 use std::default::Default;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
@@ -16,6 +17,8 @@ const NUM_THREADS: usize = 8;
 pub struct Engine<S: Searchable> {
     tt: TranspositionTable<S>,
     pool: Arc<ThreadPool>,
+    finishing: Option<Score>,
+    nodes: Arc<AtomicU64>,
 }
 
 impl<S: Searchable> Default for Engine<S> {
@@ -26,21 +29,62 @@ impl<S: Searchable> Default for Engine<S> {
 
 impl<S: Searchable> Engine<S> {
     pub fn new() -> Self {
-        let tt = TranspositionTable::new(256);
-        let pool = Arc::new(
+        Engine {
+            tt: TranspositionTable::new(256),
+            pool: Engine::<S>::new_pool(),
+            finishing: None,
+            nodes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Creates an engine that shares this engine's transposition table but has
+    /// its own search threads, so engines searching side by side learn from each
+    /// other without waiting on each other. A clone shares the threads as well.
+    pub fn share_table(&self) -> Self {
+        Engine {
+            tt: self.tt.clone(),
+            pool: Engine::<S>::new_pool(),
+            finishing: self.finishing,
+            nodes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn new_pool() -> Arc<ThreadPool> {
+        Arc::new(
             ThreadPoolBuilder::new()
                 .num_threads(NUM_THREADS - 1)
                 .build()
                 .unwrap(),
-        );
+        )
+    }
 
-        Engine { tt, pool }
+    /// Turns on finishing mode. Once the mover is ahead by at least `threshold`,
+    /// moves that keep the win and trade pieces off are preferred over the moves
+    /// with the highest score, so won games are wrapped up instead of dragged out.
+    /// A forced win is never traded for a move that only keeps a big lead.
+    pub fn finishing(mut self, threshold: Score) -> Self {
+        self.finishing = Some(threshold);
+        self
     }
 
     pub fn search(&mut self, state: &S, constraint: &SearchConstraint) -> Vec<ActionScorePair<S>> {
+        self.search_info(state, constraint).0
+    }
+
+    /// Searches like [search](#method.search) and also reports the deepest
+    /// completed iteration and how many nodes were visited
+    pub fn search_info(
+        &mut self,
+        state: &S,
+        constraint: &SearchConstraint,
+    ) -> (Vec<ActionScorePair<S>>, SearchInfo) {
         self.tt.new_search(); // increment the generation
 
-        let me = self.clone();
+        // clones share the table, but every search counts its own nodes
+        let mut me = self.clone();
+        me.nodes = Arc::new(AtomicU64::new(0));
+        let nodes = me.nodes.clone();
+
         let state = *state;
 
         // set the initial zobrist hash
@@ -71,22 +115,36 @@ impl<S: Searchable> Engine<S> {
                 Optim::Max => b.1.cmp(a.1),
             });
             // can get rid of this part..
-            results
+            let results = results
                 .into_iter()
                 .map(|(&a, &s)| ActionScorePair {
                     action: a,
                     score: s,
                 }) // copy all of the values and get rid of ordered float wrapper
                 // .take(5) // only take the top fives moves.
-                .collect()
+                .collect::<Vec<_>>();
+
+            (depth, results)
         };
 
-        match constraint {
+        let (depth, results) = match constraint {
             // have iterative deepening for None as well..
             SearchConstraint::None => compute_at_depth(13),
             SearchConstraint::Depth(dep) => compute_at_depth(*dep),
             SearchConstraint::Time(dur) => self.iddfs_helper(compute_at_depth, *dur, None),
-        }
+        };
+
+        let info = SearchInfo {
+            depth,
+            nodes: nodes.load(Ordering::Relaxed),
+        };
+
+        let results = match self.finishing {
+            Some(threshold) => Engine::finish(&state, results, threshold),
+            None => results,
+        };
+
+        (results, info)
     }
 
     pub fn reset(&mut self) {
@@ -108,6 +166,84 @@ impl<S: Searchable> Engine<S> {
         }
     }
 
+    /// Reorders the results so that among the moves that are still winning the ones
+    /// that trade off the opponent's pieces come first. Moves that give material
+    /// away for nothing go last.
+    fn finish(
+        state: &S,
+        mut results: Vec<ActionScorePair<S>>,
+        threshold: Score,
+    ) -> Vec<ActionScorePair<S>> {
+        // scores from the mover's point of view
+        let sign = match state.turn().optim() {
+            Optim::Max => 1.,
+            Optim::Min => -1.,
+        };
+        let mover_score = |p: &ActionScorePair<S>| sign * f32::from(p.score);
+
+        let best = match results.first() {
+            Some(p) => mover_score(p),
+            None => return results,
+        };
+
+        if best < f32::from(threshold) {
+            return results;
+        }
+
+        // with a forced win on the board only other forced wins will do
+        let winning = results
+            .iter()
+            .take_while(|p| {
+                let score = mover_score(p);
+                score >= f32::from(threshold) && (best.is_finite() || score.is_infinite())
+            })
+            .count();
+
+        // the sort is stable so equal trades keep the score order
+        results[..winning].sort_by_key(|p| Engine::trade(state, p.action));
+
+        results
+    }
+
+    /// Sorts the outcome of an action for the mover best first: the material
+    /// difference after the opponent's reply and the mover's recapture, then how
+    /// few pieces the opponent has left. The opponent replies to keep as much of
+    /// the difference and its own pieces as it can, but capturing is forced so
+    /// offering a piece makes the trade happen.
+    fn trade(state: &S, action: S::Action) -> (Reverse<i64>, u32) {
+        let state_p = match state.take_action(action) {
+            Ok(state_p) => state_p,
+            Err(_) => return (Reverse(i64::MIN), u32::MAX),
+        };
+
+        let mover = state.turn();
+        let opponent = state_p.turn();
+
+        let key = |s: &S| {
+            let theirs = s.material(&opponent);
+            let difference = i64::from(s.material(&mover)) - i64::from(theirs);
+            (Reverse(difference), theirs)
+        };
+
+        // the mover's best capture after the reply, if it has one
+        let recapture = |s: &S| {
+            s.generate_all_actions()
+                .iter()
+                .map(|c| c.state())
+                .filter(|c| c.material(&opponent) < s.material(&opponent))
+                .map(key)
+                .min()
+                .unwrap_or_else(|| key(s))
+        };
+
+        state_p
+            .generate_all_actions()
+            .iter()
+            .map(|r| recapture(r.state()))
+            .max()
+            .unwrap_or_else(|| key(&state_p))
+    }
+
     fn minmax_helper(
         &self,
         state: &S,
@@ -116,6 +252,8 @@ impl<S: Searchable> Engine<S> {
         mut beta: Score,
         zobrist_hash: u64,
     ) -> Score {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+
         if let Some(value) = self.tt.probe(zobrist_hash, state, depth as u8) {
             return value;
         }
@@ -188,28 +326,60 @@ impl<S: Searchable> Engine<S> {
                 None => Box::new(1..),
             };
 
-            for depth in depths_iter {
+            for (i, depth) in depths_iter.enumerate() {
                 let eval = f(depth);
 
                 // this does not do well enought at all. we are running way to much extra computation
-                match quit_rx.try_recv() {
-                    Ok(()) | Err(mpsc::TryRecvError::Disconnected) => break,
-                    Err(mpsc::TryRecvError::Empty) => (),
+                // the first depth is always sent so there is a result to fall back on
+                if i > 0 {
+                    match quit_rx.try_recv() {
+                        Ok(()) | Err(mpsc::TryRecvError::Disconnected) => break,
+                        Err(mpsc::TryRecvError::Empty) => (),
+                    }
                 }
 
                 // send result
-                eval_tx.send(eval).unwrap();
+                if eval_tx.send(eval).is_err() {
+                    break;
+                }
             }
         });
 
         // maybe make duration optional later..
         thread::sleep(duration);
 
-        quit_tx.send(()).unwrap();
+        // the search may already be done with every depth
+        let _ = quit_tx.send(());
+
+        // get the most recent move suggested by the engine. if the search could
+        // not even start in time (all search threads busy), wait for the first depth
+        // will only panic if the depth limit leaves no depths to search
+        match eval_rx.try_iter().last() {
+            Some(eval) => eval,
+            None => eval_rx.recv().unwrap(),
+        }
+    }
+}
+
+/// Represents how far a search got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchInfo {
+    depth: u32,
+    nodes: u64,
+}
 
-        // get the most recent move suggested by the engine
-        // will only panic if iddps didnt find a result (almost impossible)
-        eval_rx.try_iter().last().unwrap()
+impl SearchInfo {
+    /// Returns the depth of the deepest completed iteration
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns how many positions were visited, including any from an iteration
+    /// that was cut off by the time limit
+    #[inline]
+    pub fn nodes(&self) -> u64 {
+        self.nodes
     }
 }
 
@@ -230,6 +400,7 @@ impl<S: Searchable> ActionScorePair<S> {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub enum SearchConstraint {
     Depth(u32),
     Time(Duration),
@@ -255,3 +426,92 @@ impl SearchConstraint {
         SearchConstraint::None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Action, Bitboard};
+
+    fn results(moves: &[(&str, f32)]) -> Vec<ActionScorePair<Bitboard>> {
+        moves
+            .iter()
+            .map(|&(movetext, score)| ActionScorePair {
+                action: Action::from_movetext(movetext).unwrap(),
+                score: Score::from(score),
+            })
+            .collect()
+    }
+
+    fn movetexts(results: &[ActionScorePair<Bitboard>]) -> Vec<String> {
+        results.iter().map(|p| p.action().movetext()).collect()
+    }
+
+    #[test]
+    fn finish_test() {
+        // 14-18 trades a man for a man, 14-17 gives one away for nothing
+        let board = Bitboard::from_fen("B:W22,30:B6,10,14,K1").unwrap();
+        let threshold = Score::from(1.);
+
+        let finished = Engine::finish(
+            &board,
+            results(&[
+                ("10-15", 3.),
+                ("14-17", 2.5),
+                ("14-18", 2.),
+                ("1-5", 1.5),
+                ("6-9", 0.5),
+            ]),
+            threshold,
+        );
+        assert_eq!(
+            movetexts(&finished),
+            vec!["14-18", "10-15", "1-5", "14-17", "6-9"]
+        );
+
+        // not winning by enough to start simplifying
+        let finished = Engine::finish(
+            &board,
+            results(&[("10-15", 0.5), ("14-18", 0.2)]),
+            threshold,
+        );
+        assert_eq!(movetexts(&finished), vec!["10-15", "14-18"]);
+
+        // a forced win is kept over a trade
+        let finished = Engine::finish(
+            &board,
+            results(&[("1-5", f32::INFINITY), ("14-18", 2.)]),
+            threshold,
+        );
+        assert_eq!(movetexts(&finished), vec!["1-5", "14-18"]);
+    }
+
+    #[test]
+    fn search_info_test() {
+        let mut engine = Engine::new();
+        let board = Bitboard::default();
+
+        let (results, info) = engine.search_info(&board, &SearchConstraint::depth(3).unwrap());
+        assert_eq!(results.len(), 7);
+        assert_eq!(info.depth(), 3);
+        assert!(info.nodes() > 7);
+
+        // every search counts its nodes from zero
+        let (_, again) = engine.search_info(&board, &SearchConstraint::depth(1).unwrap());
+        assert!(again.nodes() < info.nodes());
+    }
+
+    #[test]
+    fn timed_search_test() {
+        // even without time for a single depth there is a move to play
+        let mut engine = Engine::new();
+        let board = Bitboard::default();
+        let (results, info) = engine.search_info(&board, &SearchConstraint::time(0).unwrap());
+        assert_eq!(results.len(), 7);
+        assert!(info.depth() >= 1);
+
+        let mut shared = engine.share_table();
+        assert!(!shared
+            .search(&board, &SearchConstraint::time(0).unwrap())
+            .is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use super::{ActionScorePair, Engine, SearchConstraint, Searchable};
+use crate::error::PoolError;
+
+const DEFAULT_INTERACTIVE_LIMIT: usize = 64;
+const DEFAULT_BATCH_LIMIT: usize = 256;
+
+// after this many interactive jobs in a row a waiting batch job gets a turn
+const BATCH_STARVATION_LIMIT: usize = 8;
+
+/// Represents how urgently a search submitted to an
+/// [EnginePool](struct.EnginePool.html) needs its answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Interactive,
+    Batch,
+}
+
+struct Job<S: Searchable> {
+    state: S,
+    constraint: SearchConstraint,
+    reply: mpsc::Sender<Option<Vec<ActionScorePair<S>>>>,
+}
+
+/// Jobs of a single priority. Every client gets its own queue and the clients
+/// are served round robin so one busy client cannot crowd out the others.
+struct Lane<S: Searchable> {
+    clients: VecDeque<(u64, VecDeque<Job<S>>)>,
+    len: usize,
+    limit: usize,
+}
+
+impl<S: Searchable> Lane<S> {
+    fn new(limit: usize) -> Self {
+        Lane {
+            clients: VecDeque::new(),
+            len: 0,
+            limit,
+        }
+    }
+
+    fn push(&mut self, client: u64, job: Job<S>) -> bool {
+        if self.len >= self.limit {
+            return false;
+        }
+
+        match self.clients.iter_mut().find(|(c, _)| *c == client) {
+            Some((_, jobs)) => jobs.push_back(job),
+            None => self.clients.push_back((client, vec![job].into())),
+        }
+
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<Job<S>> {
+        let (client, mut jobs) = self.clients.pop_front()?;
+        let job = jobs.pop_front();
+
+        // move the client to the back of the line if it still has work
+        if !jobs.is_empty() {
+            self.clients.push_back((client, jobs));
+        }
+
+        self.len -= 1;
+        job
+    }
+}
+
+struct Queue<S: Searchable> {
+    interactive: Lane<S>,
+    batch: Lane<S>,
+    interactive_streak: usize,
+    shutdown: bool,
+}
+
+impl<S: Searchable> Queue<S> {
+    fn lane(&mut self, priority: Priority) -> &mut Lane<S> {
+        match priority {
+            Priority::Interactive => &mut self.interactive,
+            Priority::Batch => &mut self.batch,
+        }
+    }
+
+    fn pop(&mut self) -> Option<Job<S>> {
+        // interactive jobs go first, but batch jobs are not starved forever
+        if self.batch.len > 0
+            && (self.interactive.len == 0 || self.interactive_streak >= BATCH_STARVATION_LIMIT)
+        {
+            self.interactive_streak = 0;
+            return self.batch.pop();
+        }
+
+        let job = self.interactive.pop();
+        if job.is_some() {
+            self.interactive_streak += 1;
+        }
+        job
+    }
+}
+
+type Shared<S> = Arc<(Mutex<Queue<S>>, Condvar)>;
+
+/// Multiplexes a fixed set of engines across any number of concurrent searches.
+///
+/// Searches are queued and picked up by whichever engine frees up first.
+/// Interactive searches are served before batch searches, clients are served
+/// round robin within a priority and each priority has a queue length limit, so
+/// once the pool is saturated new batch work is turned away before interactive
+/// work is.
+///
+/// # Examples
+///
+/// ```no_run
+/// use muskox::board::Bitboard;
+/// use muskox::search::{Engine, EnginePool, Priority, SearchConstraint};
+///
+/// // the engines share one transposition table but search on their own threads
+/// let engine = Engine::new();
+/// let engines = (0..4).map(|_| engine.share_table()).collect();
+/// let pool = EnginePool::new(engines).unwrap().queue_limits(16, 128);
+///
+/// let ticket = pool
+///     .submit(1, &Bitboard::default(), &SearchConstraint::depth(8).unwrap(), Priority::Interactive)
+///     .unwrap();
+/// let results = ticket.wait().unwrap();
+/// println!("{}", results[0].action());
+/// ```
+pub struct EnginePool<S: Searchable> {
+    shared: Shared<S>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<S: Searchable> EnginePool<S> {
+    /// Starts a worker thread for every engine. Returns an error if there are no
+    /// engines, since nothing would ever pick up a search.
+    pub fn new(engines: Vec<Engine<S>>) -> Result<Self, PoolError> {
+        if engines.is_empty() {
+            return Err(PoolError::NoEnginesError);
+        }
+
+        let queue = Queue {
+            interactive: Lane::new(DEFAULT_INTERACTIVE_LIMIT),
+            batch: Lane::new(DEFAULT_BATCH_LIMIT),
+            interactive_streak: 0,
+            shutdown: false,
+        };
+
+        let shared: Shared<S> = Arc::new((Mutex::new(queue), Condvar::new()));
+
+        let workers = engines
+            .into_iter()
+            .map(|engine| {
+                let shared = shared.clone();
+                thread::spawn(move || EnginePool::work(engine, shared))
+            })
+            .collect();
+
+        Ok(EnginePool { shared, workers })
+    }
+
+    /// Sets how many searches of each priority can wait in the queue
+    pub fn queue_limits(self, interactive: usize, batch: usize) -> Self {
+        {
+            let mut queue = self.shared.0.lock().unwrap();
+            queue.interactive.limit = interactive;
+            queue.batch.limit = batch;
+        }
+        self
+    }
+
+    /// Queues a search on behalf of `client`. Returns a ticket to wait on for the
+    /// results, or an error if the queue for the priority is full.
+    pub fn submit(
+        &self,
+        client: u64,
+        state: &S,
+        constraint: &SearchConstraint,
+        priority: Priority,
+    ) -> Result<Ticket<S>, PoolError> {
+        let (reply, results) = mpsc::channel();
+
+        let job = Job {
+            state: *state,
+            constraint: *constraint,
+            reply,
+        };
+
+        let (lock, ready) = &*self.shared;
+        let mut queue = lock.lock().unwrap();
+
+        if !queue.lane(priority).push(client, job) {
+            return Err(PoolError::QueueFullError { priority });
+        }
+
+        ready.notify_one();
+
+        Ok(Ticket { results })
+    }
+
+    /// Returns how many searches of a priority are waiting for an engine
+    pub fn queue_len(&self, priority: Priority) -> usize {
+        self.shared.0.lock().unwrap().lane(priority).len
+    }
+
+    #[inline]
+    pub fn num_engines(&self) -> usize {
+        self.workers.len()
+    }
+
+    fn work(mut engine: Engine<S>, shared: Shared<S>) {
+        let (lock, ready) = &*shared;
+
+        loop {
+            let job = {
+                let mut queue = lock.lock().unwrap();
+                loop {
+                    if queue.shutdown {
+                        return;
+                    }
+                    if let Some(job) = queue.pop() {
+                        break job;
+                    }
+                    queue = ready.wait(queue).unwrap();
+                }
+            };
+
+            // a search that panics fails its own job but must not take the
+            // engine down with it, or queued tickets would wait forever
+            let results = panic::catch_unwind(AssertUnwindSafe(|| {
+                engine.search(&job.state, &job.constraint)
+            }))
+            .ok();
+
+            // the client may have given up waiting, which is fine
+            let _ = job.reply.send(results);
+        }
+    }
+}
+
+impl<S: Searchable> Drop for EnginePool<S> {
+    fn drop(&mut self) {
+        {
+            let (lock, ready) = &*self.shared;
+            lock.lock().unwrap().shutdown = true;
+            ready.notify_all();
+        }
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A handle to the results of a search queued in an [EnginePool](struct.EnginePool.html)
+pub struct Ticket<S: Searchable> {
+    results: mpsc::Receiver<Option<Vec<ActionScorePair<S>>>>,
+}
+
+impl<S: Searchable> Ticket<S> {
+    /// Blocks until the search is done
+    pub fn wait(self) -> Result<Vec<ActionScorePair<S>>, PoolError> {
+        match self.results.recv() {
+            Ok(Some(results)) => Ok(results),
+            Ok(None) => Err(PoolError::SearchPanicError),
+            Err(_) => Err(PoolError::PoolClosedError),
+        }
+    }
+
+    /// Returns the results if the search is already done. A search that
+    /// panicked never yields results, use [wait](#method.wait) to see why.
+    pub fn try_wait(&self) -> Option<Vec<ActionScorePair<S>>> {
+        self.results.try_recv().ok().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Bitboard, Color};
+    use crate::search::{ActionStatePair, GameState, Score, Winner};
+
+    /// A one move game whose broken positions make the engine panic
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    struct Fragile {
+        broken: bool,
+        over: bool,
+    }
+
+    impl Searchable for Fragile {
+        type Action = ();
+        type Side = Color;
+        type ActionError = ();
+
+        fn generate_all_actions(&self) -> Vec<ActionStatePair<Self>> {
+            assert!(!self.broken, "searched a broken position");
+
+            if self.over {
+                Vec::new()
+            } else {
+                vec![ActionStatePair::new((), self.take_action(()).unwrap(), 1)]
+            }
+        }
+
+        fn take_action(&self, _: ()) -> Result<Self, ()> {
+            Ok(Fragile {
+                broken: false,
+                over: true,
+            })
+        }
+
+        fn get_game_state(&self) -> GameState<Self> {
+            if self.over {
+                GameState::Completed(Winner::Draw)
+            } else {
+                GameState::InProgress
+            }
+        }
+
+        fn turn(&self) -> Color {
+            Color::Black
+        }
+
+        fn evaluate(&self) -> Score {
+            Score::from(0.)
+        }
+
+        fn zobrist_hash(&self) -> u64 {
+            self.over as u64
+        }
+    }
+
+    fn job(depth: u32) -> Job<Bitboard> {
+        let (reply, _) = mpsc::channel();
+        Job {
+            state: Bitboard::default(),
+            constraint: SearchConstraint::depth(depth).unwrap(),
+            reply,
+        }
+    }
+
+    fn depth(job: Job<Bitboard>) -> u32 {
+        match job.constraint {
+            SearchConstraint::Depth(d) => d,
+            _ => panic!("expected a depth constraint"),
+        }
+    }
+
+    #[test]
+    fn lane_round_robin_test() {
+        let mut lane = Lane::new(4);
+        assert!(lane.push(1, job(1)));
+        assert!(lane.push(1, job(2)));
+        assert!(lane.push(1, job(3)));
+        assert!(lane.push(2, job(4)));
+        assert!(!lane.push(2, job(5)));
+
+        let order: Vec<_> = (0..4).map(|_| depth(lane.pop().unwrap())).collect();
+        assert_eq!(order, vec![1, 4, 2, 3]);
+        assert!(lane.pop().is_none());
+    }
+
+    #[test]
+    fn queue_priority_test() {
+        let mut queue = Queue {
+            interactive: Lane::new(32),
+            batch: Lane::new(32),
+            interactive_streak: 0,
+            shutdown: false,
+        };
+
+        queue.lane(Priority::Batch).push(1, job(0));
+        for i in 1..=10 {
+            queue.lane(Priority::Interactive).push(2, job(i));
+        }
+
+        let order: Vec<_> = (0..11).map(|_| depth(queue.pop().unwrap())).collect();
+        assert_eq!(order, vec![1, 2, 3, 4, 5, 6, 7, 8, 0, 9, 10]);
+    }
+
+    #[test]
+    fn pool_empty_test() {
+        match EnginePool::<Bitboard>::new(Vec::new()) {
+            Err(PoolError::NoEnginesError) => (),
+            _ => panic!("expected an error for a pool without engines"),
+        }
+    }
+
+    #[test]
+    fn pool_search_test() {
+        let engine = Engine::new();
+        let engines = (0..2).map(|_| engine.share_table()).collect();
+        let pool = EnginePool::new(engines).unwrap();
+        assert_eq!(pool.num_engines(), 2);
+
+        let constraint = SearchConstraint::depth(4).unwrap();
+        let tickets: Vec<_> = (0..4)
+            .map(|client| {
+                pool.submit(client, &Bitboard::default(), &constraint, Priority::Batch)
+                    .unwrap()
+            })
+            .collect();
+
+        for ticket in tickets {
+            // every opening move gets a score
+            assert_eq!(ticket.wait().unwrap().len(), 7);
+        }
+        assert_eq!(pool.queue_len(Priority::Batch), 0);
+    }
+
+    #[test]
+    fn pool_panic_test() {
+        let pool = EnginePool::new(vec![Engine::new()]).unwrap();
+        let constraint = SearchConstraint::depth(2).unwrap();
+
+        let broken = Fragile {
+            broken: true,
+            over: false,
+        };
+        let ticket = pool
+            .submit(1, &broken, &constraint, Priority::Interactive)
+            .unwrap();
+        match ticket.wait() {
+            Err(PoolError::SearchPanicError) => (),
+            _ => panic!("expected the search to panic"),
+        }
+
+        // the only engine survived the panic and picks up the next search
+        let ticket = pool
+            .submit(1, &Fragile::default(), &constraint, Priority::Interactive)
+            .unwrap();
+        assert_eq!(ticket.wait().unwrap().len(), 1);
+    }
+}
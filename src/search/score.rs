@@ -18,6 +18,16 @@ impl Score {
     pub const NEG_INFINITY: Score = Score {
         data: OrderedFloat(f32::NEG_INFINITY),
     };
+
+    /// Writes the score as a JSON value. JSON has no infinity, so won positions
+    /// are written as the strings `"inf"` and `"-inf"`.
+    pub(crate) fn json(&self) -> String {
+        if self.data.is_finite() {
+            self.to_string()
+        } else {
+            format!("\"{}\"", self)
+        }
+    }
 }
 
 impl From<f32> for Score {
@@ -28,6 +38,12 @@ impl From<f32> for Score {
     }
 }
 
+impl From<Score> for f32 {
+    fn from(t: Score) -> f32 {
+        t.data.0
+    }
+}
+
 impl Ord for Score {
     fn cmp(&self, other: &Self) -> Ordering {
         self.data.cmp(&other.data)
@@ -17,6 +17,13 @@ pub trait Searchable: 'static + Sized + Copy + Eq + Hash + Default + Send + Sync
     fn turn(&self) -> Self::Side;
     fn evaluate(&self) -> super::Score;
     fn zobrist_hash(&self) -> u64;
+
+    /// Returns the number of pieces `side` has left. Finishing mode uses it to
+    /// prefer trades once a game is won. Without it every move counts as keeping
+    /// all material, so finishing mode keeps the moves in score order.
+    fn material(&self, _side: &Self::Side) -> u32 {
+        0
+    }
 }
 
 pub struct ActionStatePair<S: Searchable> {
@@ -28,8 +28,10 @@ impl<S: Searchable> default::Default for TTEntry<S> {
 
 impl<S: Searchable> TTEntry<S> {
     fn replace_value(&self, current_generation: u8) -> u8 {
-        // stockfish uses 8 as the multipler
-        self.depth - 4 * (current_generation - self.generation)
+        // stockfish uses 8 as the multipler. engines sharing a table keep their
+        // own generation counters, so an entry can look newer than the search
+        let age = current_generation.wrapping_sub(self.generation);
+        self.depth.saturating_sub(age.saturating_mul(4))
     }
 }
 
@@ -65,7 +67,8 @@ impl<S: Searchable> TranspositionTable<S> {
     }
 
     pub fn new_search(&mut self) {
-        self.generation += 1;
+        // generations only matter relative to each other, see replace_value
+        self.generation = self.generation.wrapping_add(1);
     }
 
     pub fn save(&self, zobrist_hash: u64, &state: &S, depth: u8, score: Score) {
@@ -140,3 +143,19 @@ impl<S: Searchable> TranspositionTable<S> {
         self.generation = 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Bitboard;
+
+    #[test]
+    fn generation_test() {
+        // a long lived engine runs far more searches than fit in a generation
+        let mut tt = TranspositionTable::<Bitboard>::new(1);
+        for _ in 0..300 {
+            tt.new_search();
+        }
+        assert_eq!(tt.generation, (301 % 256) as u8);
+    }
+}